@@ -1,30 +1,75 @@
+use std::sync::atomic::AtomicBool;
 use chrono::Utc;
 use serde::{Serialize, Deserialize};
 
-use crate::{ProofOfWork, Transaction, utils::{serialize, hash_to_str}};
+use crate::{Amount, Consensus, GenesisParams, MerkleProof, MerkleTree, Miner, Transaction, VERSIONBITS_TOP_BITS, utils::{serialize, hash_to_str}, error::BlockchainError};
 
 /* 区块头定义 */
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
 pub struct BlockHeader {
+    version: u32,       // 区块版本，同时用于软分叉部署的信号
     timestamp: i64,     // 当前块的时间戳
     prev_hash: String,  // 前一个块的hash
-    txs_hash: String,   // 区块中所有交易的hash，后续可优化为Merkle tree
-    bits: usize,        // 工作量证明的难度，hash的前bits位必须为0
+    txs_hash: String,   // 区块中所有交易构成的Merkle树的根
+    bits: u32,          // 工作量证明的难度，target的紧凑编码，hash必须小于target
     nonce: usize,       // 迭代挖矿的次数
+    seal: Vec<u8>,      // 共识机制的封装数据，例如权威证明中验证者的签名，工作量证明中为空
 }
 
 /* 区块头功能封装 */
 impl BlockHeader {
     // 构造函数
-    fn new(prev_hash: &str, bits: usize) -> Self {
+    fn new(prev_hash: &str, bits: u32) -> Self {
         Self {
+            version: VERSIONBITS_TOP_BITS,
             timestamp: Utc::now().timestamp(),
             prev_hash: prev_hash.into(),
             txs_hash: String::new(),
             bits,
             nonce: 0,
+            seal: vec![],
         }
     }
+
+    // 区块头的hash，即区块的hash
+    pub fn hash(&self) -> String {
+        serialize(self).map(|header_ser| hash_to_str(&header_ser)).unwrap_or_default()
+    }
+
+    // 不包含seal的区块头的hash，即权威证明中验证者签名的内容
+    pub fn seal_hash(&self) -> String {
+        let mut header = self.clone();
+        header.seal = vec![];
+        header.hash()
+    }
+
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn get_timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    pub fn get_prev_hash(&self) -> String {
+        self.prev_hash.clone()
+    }
+
+    pub fn get_txs_hash(&self) -> String {
+        self.txs_hash.clone()
+    }
+
+    pub fn get_bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn get_seal(&self) -> &[u8] {
+        &self.seal
+    }
+
+    pub fn set_nonce(&mut self, nonce: usize) {
+        self.nonce = nonce;
+    }
 }
 
 /* 区块定义：包括区块头，区块体（data），当前区块hash */
@@ -37,38 +82,77 @@ pub struct Block {
 
 /* 区块功能封装 */
 impl Block {
-    // 构造函数, 生成新的区块
-    pub fn new(txs: &[Transaction], pre_hash: &str, bits: usize) -> Self {
+    // 构造函数, 生成新的区块，由网络配置的共识引擎封装
+    pub fn new(txs: &[Transaction], pre_hash: &str, bits: u32, consensus: &dyn Consensus) -> Result<Self, BlockchainError> {
+        let mut block = Self::new_template(txs, pre_hash, bits);
+
+        if !consensus.seal(&mut block, &AtomicBool::new(false)) {
+            return Err(BlockchainError::InvalidSeal(block.get_header().hash()));
+        }
+
+        Ok(block)
+    }
+
+    // 生成尚未挖矿的区块模板，nonce和hash由挖矿引擎填充
+    pub fn new_template(txs: &[Transaction], pre_hash: &str, bits: u32) -> Self {
         let mut block = Block {
             header: BlockHeader::new(pre_hash, bits),
             tranxs: txs.to_vec(),
             hash: String::new(),
         };
         block.set_txs_hash(txs);
-
-        // 工作量证明
-        let pow = ProofOfWork::new(bits);
-        pow.run(&mut block);
-
         block
     }
     
-    // 静态方法：生成创世块，prev_hash为空
-    pub fn create_genesis(bits: usize, genesis_addr: &str) -> Self {
-        let coinbase = Transaction::new_coinbase(genesis_addr);
-        Self::new(&vec![coinbase], "", bits)
+    // 静态方法：根据网络参数生成创世块，prev_hash为空
+    // 时间戳固定，且单线程从0开始搜索nonce，保证所有节点生成的创世块相同
+    pub fn create_genesis(genesis: &GenesisParams, bits: u32, reward: Amount) -> Self {
+        let coinbase = Transaction::new_coinbase(&genesis.address, reward, 1, &genesis.coinbase_data);
+        let mut block = Self::new_template(&[coinbase], "", bits);
+        block.header.timestamp = genesis.timestamp;
+        Miner::new(1).mine(&mut block, &AtomicBool::new(false));
+        block
     }
 
-    // 私有方法：对交易集合签名
+    // 私有方法：以交易集合的Merkle树根作为txs_hash
     fn set_txs_hash(&mut self, txs: &[Transaction]) {
-        if let Ok(txs_ser) = serialize(txs) {
-            self.header.txs_hash = hash_to_str(&txs_ser);
-        }
+        self.header.txs_hash = MerkleTree::from_transactions(txs).get_root();
+    }
+
+    // 校验区块头中的txs_hash是否与区块中的交易集合一致
+    pub fn verify_txs_hash(&self) -> bool {
+        MerkleTree::from_transactions(&self.tranxs).get_root() == self.header.txs_hash
+    }
+
+    // 区块序列化之后的字节数，包括区块头和所有交易，不包括由区块头计算得出的hash
+    pub fn size(&self) -> usize {
+        serialize(&(&self.header, &self.tranxs)).map_or(0, |block_ser| block_ser.len())
+    }
+
+    // 生成区块中某笔交易的Merkle包含证明
+    pub fn get_merkle_proof(&self, txid: &str) -> Option<MerkleProof> {
+        let tx = self.tranxs.iter().find(|tx| tx.get_id() == txid)?;
+        MerkleTree::from_transactions(&self.tranxs).get_proof(&tx.get_full_hash())
     }
 
     /* 共有方法 */
     pub fn set_nonce(&mut self, nonce: usize) {
-        self.header.nonce = nonce;
+        self.header.set_nonce(nonce);
+    }
+
+    // 设置共识机制的封装数据，由共识引擎在封装区块时调用
+    pub fn set_seal(&mut self, seal: Vec<u8>) {
+        self.header.seal = seal;
+    }
+
+    // 设置区块时间戳，只能在挖矿之前调用
+    pub fn set_timestamp(&mut self, timestamp: i64) {
+        self.header.timestamp = timestamp;
+    }
+
+    // 设置区块版本，只能在挖矿之前调用
+    pub fn set_version(&mut self, version: u32) {
+        self.header.version = version;
     }
     
     pub fn get_header(&self) -> BlockHeader {
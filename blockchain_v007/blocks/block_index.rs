@@ -0,0 +1,29 @@
+use bigint::U256;
+use serde::{Serialize, Deserialize};
+
+/*
+ * 区块索引：记录区块所在的高度，以及从创世块到该区块的累计工作量。
+ * 主链和侧链上的每个区块都有一个索引，用于选择累计工作量最大的链。
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BlockIndex {
+    height: usize,          // 区块高度，创世块为1
+    chain_work: [u8; 32],   // 累计工作量，U256的大端字节序
+}
+
+impl BlockIndex {
+    pub fn new(height: usize, chain_work: U256) -> Self {
+        Self {
+            height,
+            chain_work: chain_work.into(),
+        }
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_chain_work(&self) -> U256 {
+        U256::from(self.chain_work)
+    }
+}
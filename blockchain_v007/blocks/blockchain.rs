@@ -10,87 +10,772 @@
  * 7. P2P网络功能，基于libp2p类库
  * 8. 增加mempool，并修复若干bugs
  */
-use std::{collections::HashMap, sync::{Arc, RwLock, atomic::{AtomicUsize, Ordering}}};
+use std::{collections::{HashMap, HashSet}, sync::{Arc, RwLock, atomic::{AtomicBool, AtomicUsize, Ordering}}};
 
-use crate::{Block, Transaction, TxOutput, RocksDb, KVStorage, error::BlockchainError};
+use crate::{Amount, Block, BlockHeader, BlockIndex, ChainParams, Consensus, Deployment, DeploymentState, DEPLOYMENT_CSV, TimeData, create_consensus, check_block_time, median_time_past, VERSIONBITS_TOP_BITS, VERSIONBITS_TOP_MASK, MerkleProof, Transaction, RelativeLock, UtxoEntry, MAX_COINBASE_DATA, RocksDb, KVStorage, ProofOfWork, error::BlockchainError};
 
 
-// 常量，工作量证明的难度
-const CURR_BITS: usize = 2;
+/* 添加一个区块之后，区块链发生的变化 */
+pub enum ChainUpdate {
+    Existed,            // 区块已经存在
+    Extended(Block),    // 区块接在tip之后，成为新的tip
+    SideChain,          // 区块存储在侧链上，主链不变
+    Reorganized {       // 侧链的累计工作量超过主链，发生重组
+        disconnected: Vec<Block>,   // 从主链上断开的区块，从旧tip开始倒序
+        connected: Vec<Block>,      // 连接到主链上的区块，按高度顺序
+    },
+}
 
 /* 区块链定义：包括区块数组，当前区块高度 */
 pub struct Blockchain<T = RocksDb> {
     storage: Arc<T>,            // 区块链的存储
     tip: Arc<RwLock<String>>,   // 当前区块链最后一个区块的hash
     height: AtomicUsize,        // 当前区块链的高度
+    params: ChainParams,        // 区块链网络参数
+    time_data: TimeData,        // 网络调整时间
+    consensus: Arc<dyn Consensus>,  // 共识引擎
+    assumed_valid: RwLock<HashSet<String>>,    // 已经确认是假定有效区块祖先的区块hash
+    deployment_states: RwLock<HashMap<(String, String), DeploymentState>>,  // 软分叉部署在每个窗口最后一个区块之后的状态
 }
 
 impl<T: KVStorage> Blockchain<T> {
     pub fn new(storage: Arc<T>) -> Self {
+        Self::with_params(storage, ChainParams::default())
+    }
+
+    // 使用网络参数对应的共识引擎，权威证明网络中只能验证区块，不能封装区块
+    pub fn with_params(storage: Arc<T>, params: ChainParams) -> Self {
+        let consensus = create_consensus(&params, None);
+        Self::with_consensus(storage, params, consensus)
+    }
+
+    pub fn with_consensus(storage: Arc<T>, params: ChainParams, consensus: Arc<dyn Consensus>) -> Self {
         // 如果db中已经存储了区块链，则加载到内存
-        if let Ok(Some(tip)) = storage.get_tip() {
+        let bc = if let Ok(Some(tip)) = storage.get_tip() {
             let height = storage.get_height().unwrap();
             Self {
                 storage,
                 tip: Arc::new(RwLock::new(tip)),
                 height: AtomicUsize::new(height.unwrap()),
+                params,
+                time_data: TimeData::default(),
+                consensus,
+                assumed_valid: RwLock::new(HashSet::new()),
+                deployment_states: RwLock::new(HashMap::new()),
             }
         } else {
             Self {
                 storage,
                 tip: Arc::new(RwLock::new(String::new())),
                 height: AtomicUsize::new(0),
+                params,
+                time_data: TimeData::default(),
+                consensus,
+                assumed_valid: RwLock::new(HashSet::new()),
+                deployment_states: RwLock::new(HashMap::new()),
             }
-        }
+        };
+        bc.record_assumed_valid(|hash| bc.find_block(hash).map(|block| block.get_header()));
+        bc
     }
 
-    // 生成创世块
-    pub fn create_genesis_block(&mut self, genesis_addr: &str) {
-        let genesis_block = Block::create_genesis(CURR_BITS, genesis_addr);
-        let hash = genesis_block.get_hash();
-        self.height.fetch_add(1, Ordering::Relaxed);
-        self.storage.update_blocks(&hash, &genesis_block, self.height.load(Ordering::Relaxed));
-        let mut tip = self.tip.write().unwrap();
-        *tip = hash;
+    // 根据网络参数生成创世块
+    pub fn create_genesis_block(&mut self) -> Result<(), BlockchainError> {
+        let bits = self.params.difficulty.initial_bits;
+        let genesis_block = self.params.genesis_block();
+        let index = BlockIndex::new(1, ProofOfWork::work(bits));
+        self.set_best_block(&genesis_block, &index)
     }
 
     /* 
      * 挖矿示例：
-     * 1. 遍历BlockHead中的nonce，以满足挖矿难度
-     * 2. 生成一个新的区块并添加在当前区块链尾部 
+     * 1. 在当前tip之上生成区块模板，coinbase交易的挖矿奖励和手续费发给miner_addr
+     * 2. 由共识引擎封装区块，例如遍历BlockHead中的nonce，以满足挖矿难度
+     * 3. 新区块与其他节点的区块一样，经过完整的验证后添加在当前区块链尾部
      */
-    pub fn mining(&mut self, txs: &[Transaction]) -> Block {
-        // 逐笔验证交易
+    pub fn mining(&mut self, miner_addr: &str, txs: &[Transaction]) -> Result<Block, BlockchainError> {
+        // 逐笔验证交易，区块模板会跳过无效的交易，这里直接返回错误
         for tx in txs {
-            if tx.verify(self) == false {
-                panic!("ERROR: Invalid transaction")
+            self.verify_transaction(tx)?;
+        }
+        let mut block = self.new_block_template(miner_addr, txs, self.params.max_block_size, &[])?;
+        // 交易之间花费同一个输出，或者超出区块大小的限制
+        let selected = block.get_transactions();
+        if let Some(tx) = txs.iter().find(|tx| !selected.iter().any(|selected| selected.get_id() == tx.get_id())) {
+            return Err(BlockchainError::InvalidTransaction(tx.get_id()));
+        }
+        if !self.consensus.seal(&mut block, &AtomicBool::new(false)) {
+            return Err(BlockchainError::InvalidSeal(block.get_header().hash()));
+        }
+        self.add_block(block.clone())?;
+
+        Ok(block)
+    }
+
+    /*
+     * 在当前tip之上生成尚未挖矿的区块模板，交给挖矿引擎异步挖矿：
+     * 1. 候选交易按手续费率（手续费/交易字节数）从高到低排序
+     * 2. 依次加入区块，跳过无效的交易、与已选交易花费同一输出的交易，以及使区块超出max_size或签名操作上限的交易
+     *    max_size不能超过共识规则允许的最大区块字节数
+     * 3. coinbase交易领取该高度的挖矿奖励和所有入选交易的手续费，并写入区块高度和coinbase_data
+     */
+    pub fn new_block_template(&self, miner_addr: &str, candidates: &[Transaction], max_size: usize, coinbase_data: &[u8]) -> Result<Block, BlockchainError> {
+        if coinbase_data.len() > MAX_COINBASE_DATA {
+            return Err(BlockchainError::InvalidCoinbase);
+        }
+
+        let tip = self.get_tip();
+        let bits = self.expected_bits(&tip)?;
+        let height = self.get_height() + 1;
+        let max_size = max_size.min(self.params.max_block_size);
+        let sequence_locks = self.is_deployment_active(DEPLOYMENT_CSV, &tip)?;
+        let mut entries: Vec<(&Transaction, Amount, usize)> = candidates.iter()
+            .filter(|tx| !tx.is_coinbase() && self.check_transaction(tx, true, sequence_locks).is_ok())
+            .filter_map(|tx| self.get_fee(tx).ok().map(|fee| (tx, fee, tx.size().max(1))))
+            .collect();
+        // fee_a / size_a > fee_b / size_b 等价于 fee_a * size_b > fee_b * size_a
+        entries.sort_by(|(_, fee_a, size_a), (_, fee_b, size_b)| {
+            (fee_b.as_sat() as u128 * *size_a as u128).cmp(&(fee_a.as_sat() as u128 * *size_b as u128))
+        });
+
+        let mut selected = vec![];
+        let mut spent = HashSet::new();
+        let mut fees = Amount::ZERO;
+        let mut sigops = 0;
+        // 只包含coinbase的区块大小，coinbase的奖励金额不影响序列化之后的字节数
+        let coinbase = Transaction::new_coinbase(miner_addr, Amount::ZERO, height, coinbase_data);
+        let mut size = Block::new_template(&[coinbase], &tip, bits).size();
+        for (tx, fee, tx_size) in entries {
+            let tx_sigops = tx.sigop_count();
+            if size + tx_size > max_size || sigops + tx_sigops > self.params.max_block_sigops {
+                continue;
+            }
+            let outpoints: Vec<(String, usize)> = tx.get_vin().iter()
+                .map(|vin| (vin.get_txid(), vin.get_vout()))
+                .collect();
+            if outpoints.iter().any(|outpoint| spent.contains(outpoint)) {
+                continue;
             }
+            let total_fees = match fees.checked_add(fee) {
+                Some(total_fees) => total_fees,
+                None => continue,
+            };
+            spent.extend(outpoints);
+            size += tx_size;
+            sigops += tx_sigops;
+            fees = total_fees;
+            selected.push(tx.clone());
         }
-        let block = Block::new(txs, &self.tip.read().unwrap(), CURR_BITS);
-        let hash = block.get_hash();
-        self.height.fetch_add(1, Ordering::Relaxed);
-        self.storage.update_blocks(&hash, &block, self.height.load(Ordering::Relaxed));
 
-        let mut tip = self.tip.write().unwrap();
-        *tip = hash;
+        // coinbase transaction必须是block中第一条交易
+        let subsidy = self.params.subsidy.subsidy(height);
+        let mut txs = vec![Transaction::new_coinbase(miner_addr, subsidy.saturating_add(fees), height, coinbase_data)];
+        txs.extend(selected);
 
-        block
+        let states = self.params.versionbits.deployments.iter()
+            .map(|deployment| self.deployment_state(deployment, &tip))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut block = Block::new_template(&txs, &tip, bits);
+        block.set_timestamp(self.next_block_time(&tip)?);
+        block.set_version(self.params.versionbits.compute_version(&states));
+        Ok(block)
     }
 
-    // 在当前区块链尾部添加新块，这个块是由其他节点挖到的
-    pub fn add_block(&mut self, block: Block) -> Result<(), BlockchainError> {
+    /*
+     * 添加其他节点挖到的新块：
+     * 1. 区块必须先通过与上下文无关的检查
+     * 2. 已经验证失败的区块，以及父区块无效的区块直接拒绝
+     * 3. 如果区块接在tip之后，验证交易后成为新的tip，验证失败则标记为无效
+     * 4. 否则区块存储在侧链上；若侧链的累计工作量超过主链，则发生重组
+     */
+    pub fn add_block(&mut self, block: Block) -> Result<ChainUpdate, BlockchainError> {
         let hash = block.get_hash();
-        if let Some(_) = self.storage.get_block(&hash)? {
+        if self.storage.is_invalid_block(&hash)? {
+            return Err(BlockchainError::InvalidChain(hash));
+        }
+        if self.storage.get_block(&hash)?.is_some() {
             println!("Block {} already exists", hash);
+            return Ok(ChainUpdate::Existed);
+        }
+
+        let index = self.next_block_index(&block)?;
+        self.check_checkpoint(&hash, index.get_height())?;
+        self.check_difficulty(&block)?;
+        self.check_time(&block)?;
+        self.check_block(&block)?;
+
+        // check_block已经确认区块hash由区块头计算得出，区块头通过Merkle树承诺了包含签名的全部交易，且没有重复交易，
+        // 之后的验证失败是该hash对应区块本身的问题，才能按hash标记，否则伪造的副本会使正常的区块及其后代被永久拒绝
+        if self.storage.is_invalid_block(&block.get_prev_hash())? {
+            self.storage.put_invalid_block(&hash);
+            return Err(BlockchainError::InvalidChain(hash));
+        }
+
+        if block.get_prev_hash() == self.get_tip() {
+            if let Err(e) = self.validate_block(&block) {
+                self.storage.put_invalid_block(&hash);
+                return Err(e);
+            }
+            self.set_best_block(&block, &index)?;
+            return Ok(ChainUpdate::Extended(block));
+        }
+
+        // 侧链上的区块，先存储下来。区块内容已经由区块头承诺，不会以正常区块的hash存储篡改过的交易
+        self.storage.put_block(&hash, &block);
+        self.storage.put_block_index(&hash, &index);
+
+        let tip_work = self.get_block_index(&self.get_tip())?.get_chain_work();
+        if index.get_chain_work() > tip_work {
+            self.reorganize(&hash)
         } else {
-            self.height.fetch_add(1, Ordering::Relaxed);
-            self.storage.update_blocks(&hash, &block, self.height.load(Ordering::Relaxed));
-            let mut tip = self.tip.write().unwrap();
-            *tip = hash;
+            Ok(ChainUpdate::SideChain)
+        }
+    }
+
+    /*
+     * 区块链重组：将tip切换到累计工作量更大的侧链上
+     * 1. 从新旧两个tip同时向前回溯，找到分叉点
+     * 2. 断开旧链上分叉点之后的区块，UTXO集合随之回退
+     * 3. 按顺序验证并连接新链上的区块，任何一个区块验证失败则断开已连接的区块，恢复旧链，
+     *    并将验证失败的区块及其之后的区块标记为无效
+     */
+    fn reorganize(&mut self, new_tip: &str) -> Result<ChainUpdate, BlockchainError> {
+        let old_tip = self.get_tip();
+
+        let mut disconnected = vec![];
+        let mut connected = vec![];
+        let mut old_block = self.get_block(&old_tip)?;
+        let mut new_block = self.get_block(new_tip)?;
+        let mut old_index = self.get_block_index(&old_tip)?;
+        let mut new_index = self.get_block_index(new_tip)?;
+
+        while old_block.get_hash() != new_block.get_hash() {
+            if new_index.get_height() >= old_index.get_height() {
+                let prev_hash = new_block.get_prev_hash();
+                connected.push(new_block);
+                new_block = self.get_block(&prev_hash)?;
+                new_index = self.get_block_index(&prev_hash)?;
+            } else {
+                let prev_hash = old_block.get_prev_hash();
+                disconnected.push(old_block);
+                old_block = self.get_block(&prev_hash)?;
+                old_index = self.get_block_index(&prev_hash)?;
+            }
+        }
+        connected.reverse();
+
+        // 新链上的区块在其他分支上已经验证失败，其后的区块都是无效区块的后代
+        for (idx, block) in connected.iter().enumerate() {
+            if self.storage.is_invalid_block(&block.get_hash())? {
+                self.mark_invalid(&connected[idx..]);
+                return Err(BlockchainError::InvalidChain(new_tip.to_string()));
+            }
+        }
+
+        // 断开旧链，tip回退到分叉点
+        for block in &disconnected {
+            self.disconnect_block(block)?;
+        }
+
+        // 逐个连接新链上的区块
+        for (idx, block) in connected.iter().enumerate() {
+            if let Err(e) = self.validate_block(block) {
+                self.mark_invalid(&connected[idx..]);
+                for block in connected[..idx].iter().rev() {
+                    self.disconnect_block(block)?;
+                }
+                for block in disconnected.iter().rev() {
+                    let height = self.get_block_index(&block.get_hash())?.get_height();
+                    self.connect_block(block, height)?;
+                }
+                return Err(e);
+            }
+            let height = self.get_block_index(&block.get_hash())?.get_height();
+            self.connect_block(block, height)?;
+        }
+
+        Ok(ChainUpdate::Reorganized { disconnected, connected })
+    }
+
+    // 标记无效的区块，blocks中第一个区块验证失败，其余区块是它的后代
+    fn mark_invalid(&self, blocks: &[Block]) {
+        for block in blocks {
+            self.storage.put_invalid_block(&block.get_hash());
+        }
+    }
+
+    /*
+     * 与上下文无关的区块检查，侧链上的区块也必须满足：
+     * 1. 区块版本必须使用版本位格式
+     * 2. 区块hash必须由区块头计算得出，并且区块头的封装必须通过共识引擎的验证
+     * 3. 每笔交易的id必须由交易内容计算得出，txs_hash必须与区块中的交易集合一致
+     * 4. 第一条交易必须是coinbase，且只能有一条coinbase
+     * 5. 区块大小和签名操作数量不能超过共识规则的上限
+     */
+    pub fn check_block(&self, block: &Block) -> Result<(), BlockchainError> {
+        let version = block.get_header().get_version();
+        if version & VERSIONBITS_TOP_MASK != VERSIONBITS_TOP_BITS {
+            return Err(BlockchainError::InvalidBlockVersion(version));
+        }
+
+        let bits = block.get_header().get_bits();
+        if !self.params.difficulty.is_valid_bits(bits) {
+            return Err(BlockchainError::InvalidDifficulty(bits));
+        }
+        if block.get_header().hash() != block.get_hash() {
+            return Err(BlockchainError::InvalidBlockHash(block.get_hash()));
+        }
+        // 创世块由网络参数确定，不需要验证封装
+        if !block.get_prev_hash().is_empty() {
+            self.consensus.verify_seal(&block.get_header())?;
+        }
+
+        // 交易id必须由交易内容计算得出，Merkle树的叶子节点是包含签名的完整交易hash，区块hash同时承诺交易的签名
+        let txs = block.get_transactions();
+        if let Some(tx) = txs.iter().find(|tx| !tx.verify_hash()) {
+            return Err(BlockchainError::InvalidTransaction(tx.get_id()));
+        }
+        // Merkle树奇数层复制最后一个节点，重复末尾交易的区块与原区块的txs_hash相同(CVE-2012-2459)
+        // 必须在标记无效区块之前拒绝，否则伪造的副本会让正常区块也被标记为无效
+        let mut txids = HashSet::new();
+        if let Some(tx) = txs.iter().find(|tx| !txids.insert(tx.get_id())) {
+            return Err(BlockchainError::DuplicateTransaction(tx.get_id()));
+        }
+        if !block.verify_txs_hash() {
+            return Err(BlockchainError::InvalidTxsHash);
+        }
+
+        match txs.split_first() {
+            Some((coinbase, rest)) if coinbase.is_coinbase() => {
+                if rest.iter().any(|tx| tx.is_coinbase()) {
+                    return Err(BlockchainError::InvalidCoinbase);
+                }
+            },
+            _ => return Err(BlockchainError::InvalidCoinbase),
+        }
+
+        let size = block.size();
+        if size > self.params.max_block_size {
+            return Err(BlockchainError::BlockTooLarge(size, self.params.max_block_size));
+        }
+        let sigops: usize = txs.iter().map(|tx| tx.sigop_count()).sum();
+        if sigops > self.params.max_block_sigops {
+            return Err(BlockchainError::TooManySigops(sigops, self.params.max_block_sigops));
+        }
+
+        Ok(())
+    }
+
+    /*
+     * 在当前tip之上验证区块：
+     * 1. prev_hash必须指向当前区块链的tip
+     * 2. 除coinbase外的交易必须通过验证，假定有效区块的祖先区块不验证签名，相对时间锁在csv部署生效后才检查
+     * 3. 区块中的交易不能花费同一个输出
+     * 4. coinbase中写入的高度必须等于区块的高度
+     * 5. coinbase的输出不能超过该高度的挖矿奖励与所有交易手续费之和
+     */
+    pub fn validate_block(&self, block: &Block) -> Result<(), BlockchainError> {
+        let tip = self.get_tip();
+        if block.get_prev_hash() != tip {
+            return Err(BlockchainError::PrevHashMismatch(block.get_prev_hash(), tip));
+        }
+
+        let height = self.get_height() + 1;
+        let txs = block.get_transactions();
+        if txs[0].get_coinbase_height() != Some(height) {
+            return Err(BlockchainError::InvalidCoinbaseHeight(height));
+        }
+
+        let check_signature = !self.is_assumed_valid(&block.get_hash());
+        let sequence_locks = self.is_deployment_active(DEPLOYMENT_CSV, &tip)?;
+        let mut spent = HashSet::new();
+        let mut fees = Amount::ZERO;
+        for tx in txs.iter().skip(1) {
+            self.check_transaction(tx, check_signature, sequence_locks)?;
+            for vin in tx.get_vin() {
+                if !spent.insert((vin.get_txid(), vin.get_vout())) {
+                    return Err(BlockchainError::DoubleSpend(vin.get_txid(), vin.get_vout()));
+                }
+            }
+            fees = fees.checked_add(self.get_fee(tx)?)
+                .ok_or_else(|| BlockchainError::InvalidTransaction(tx.get_id()))?;
+        }
+
+        let reward = txs[0].get_output_value()
+            .ok_or_else(|| BlockchainError::InvalidTransaction(txs[0].get_id()))?;
+        let allowed = self.params.subsidy.subsidy(height).saturating_add(fees);
+        if reward > allowed {
+            return Err(BlockchainError::CoinbaseOverpays(reward, allowed));
+        }
+
+        Ok(())
+    }
+
+    /*
+     * 基于当前主链验证一笔交易，交易将被打包在tip之后的下一个区块中：
+     * 1. 交易id必须由交易内容计算得出
+     * 2. 输入引用的交易输出必须在UTXO集合中，即存在于主链上且尚未被花费，同一笔交易不能重复花费一个输出
+     * 3. 引用coinbase交易的输出时，该coinbase必须已经成熟
+     * 4. 交易的lock_time已经到达，且csv部署生效后每个输入的相对时间锁已经到期
+     * 5. 签名正确，且输出不超过输入
+     */
+    pub fn verify_transaction(&self, tx: &Transaction) -> Result<(), BlockchainError> {
+        let sequence_locks = self.is_deployment_active(DEPLOYMENT_CSV, &self.get_tip())?;
+        self.check_transaction(tx, true, sequence_locks)
+    }
+
+    // 验证交易，check_signature为false时跳过签名验证，sequence_locks为false时不检查相对时间锁
+    fn check_transaction(&self, tx: &Transaction, check_signature: bool, sequence_locks: bool) -> Result<(), BlockchainError> {
+        if tx.is_coinbase() || !tx.verify_hash() {
+            return Err(BlockchainError::InvalidTransaction(tx.get_id()));
+        }
+
+        // 时间锁按下一个区块的父区块，即当前tip的中位时间计算
+        let spend_height = self.get_height() + 1;
+        let median_time = self.median_time_past(&self.get_tip())?;
+        if !tx.is_final(spend_height, median_time) {
+            return Err(BlockchainError::NonFinalTransaction(tx.get_id()));
+        }
+
+        let mut spent = HashSet::new();
+        for vin in tx.get_vin() {
+            if !spent.insert((vin.get_txid(), vin.get_vout())) {
+                return Err(BlockchainError::DoubleSpend(vin.get_txid(), vin.get_vout()));
+            }
+            let entry = self.get_utxo(&vin.get_txid(), vin.get_vout())
+                .ok_or_else(|| BlockchainError::MissingPrevTx(vin.get_txid()))?;
+            if !entry.is_mature(spend_height, self.params.coinbase_maturity) {
+                return Err(BlockchainError::InvalidTransaction(tx.get_id()));
+            }
+
+            // 相对时间锁从输出所在区块开始计算，按时间计算时使用该区块父区块的中位时间
+            let height = entry.get_height();
+            let unlocked = match vin.get_relative_lock().filter(|_| sequence_locks) {
+                Some(RelativeLock::Blocks(blocks)) => spend_height >= height + blocks,
+                Some(RelativeLock::Seconds(seconds)) => {
+                    let confirmed_time = if height <= 1 {
+                        self.params.genesis.timestamp
+                    } else {
+                        self.median_time_past(&self.get_main_chain_hash(height - 1)?)?
+                    };
+                    median_time >= confirmed_time + seconds
+                },
+                None => true,
+            };
+            if !unlocked {
+                return Err(BlockchainError::SequenceLocked(tx.get_id()));
+            }
+        }
+        self.get_fee(tx)?;
+        if check_signature {
+            tx.verify(self)?;
         }
         Ok(())
     }
 
+    // 交易的手续费：所有输入引用的未花费输出金额之和，减去所有输出的金额之和，金额之和溢出或者输出超过输入时交易无效
+    pub fn get_fee(&self, tx: &Transaction) -> Result<Amount, BlockchainError> {
+        if tx.is_coinbase() {
+            return Ok(Amount::ZERO);
+        }
+
+        let mut input_value = Amount::ZERO;
+        for vin in tx.get_vin() {
+            input_value = self.get_utxo(&vin.get_txid(), vin.get_vout())
+                .and_then(|entry| input_value.checked_add(entry.get_output().get_value()))
+                .ok_or_else(|| BlockchainError::InvalidTransaction(tx.get_id()))?;
+        }
+
+        tx.get_output_value()
+            .and_then(|output_value| input_value.checked_sub(output_value))
+            .ok_or_else(|| BlockchainError::InvalidTransaction(tx.get_id()))
+    }
+
+    /*
+     * 检查点规则：
+     * 1. 检查点高度上的区块必须是检查点指定的区块
+     * 2. 主链已经到达某个检查点之后，不再接受该检查点及之前高度上的分叉区块
+     */
+    fn check_checkpoint(&self, hash: &str, height: usize) -> Result<(), BlockchainError> {
+        if let Some(checkpoint) = self.params.get_checkpoint(height) {
+            if checkpoint != hash {
+                return Err(BlockchainError::CheckpointMismatch(height, hash.to_string()));
+            }
+        }
+        if height <= self.params.last_checkpoint_height(self.get_height()) {
+            return Err(BlockchainError::ForkBelowCheckpoint(height));
+        }
+        Ok(())
+    }
+
+    // 区块的难度必须等于根据其祖先区块计算出的难度
+    fn check_difficulty(&self, block: &Block) -> Result<(), BlockchainError> {
+        let bits = block.get_header().get_bits();
+        if bits != self.expected_bits(&block.get_prev_hash())? {
+            return Err(BlockchainError::InvalidDifficulty(bits));
+        }
+        Ok(())
+    }
+
+    // 计算接在prev_hash之后的下一个区块的难度，prev_hash为空表示创世块
+    pub fn expected_bits(&self, prev_hash: &str) -> Result<u32, BlockchainError> {
+        if prev_hash.is_empty() {
+            return Ok(self.params.difficulty.initial_bits);
+        }
+
+        let parent = self.get_block(prev_hash)?.get_header();
+        let parent_height = self.get_block_index(prev_hash)?.get_height();
+        let bits = self.consensus.next_bits(&parent, parent_height, &|hash| {
+            self.storage.get_block(hash).ok().flatten().map(|block| block.get_header())
+        });
+        Ok(bits)
+    }
+
+    // 区块时间戳必须大于其祖先区块的中位时间，且不能超前网络调整时间太多
+    fn check_time(&self, block: &Block) -> Result<(), BlockchainError> {
+        let prev_hash = block.get_prev_hash();
+        if prev_hash.is_empty() {
+            return Ok(());
+        }
+        let median_time = self.median_time_past(&prev_hash)?;
+        check_block_time(&block.get_header(), median_time, self.time_data.adjusted_time())
+    }
+
+    // 区块prev_hash之后下一个区块的中位时间
+    pub fn median_time_past(&self, prev_hash: &str) -> Result<i64, BlockchainError> {
+        let parent = self.get_block(prev_hash)?.get_header();
+        Ok(median_time_past(&parent, |hash| self.find_block(hash).map(|block| block.get_header())))
+    }
+
+    // 在prev_hash之后挖矿的新区块使用的时间戳：网络调整时间，但至少比中位时间大1秒
+    fn next_block_time(&self, prev_hash: &str) -> Result<i64, BlockchainError> {
+        Ok(self.time_data.adjusted_time().max(self.median_time_past(prev_hash)? + 1))
+    }
+
+    /*
+     * 软分叉部署在区块prev_hash之后的下一个区块上的状态
+     *     状态只在窗口边界上变化，按窗口最后一个区块的hash缓存。先回溯到prev_hash之前最近的窗口边界，
+     * 再逐个窗口回溯到已经缓存的边界，统计沿途每个窗口的信号数，然后从最早的窗口开始依次计算并缓存状态。
+     */
+    fn deployment_state(&self, deployment: &Deployment, prev_hash: &str) -> Result<DeploymentState, BlockchainError> {
+        let window = self.params.versionbits.window;
+        if window == 0 || prev_hash.is_empty() {
+            return Ok(DeploymentState::Defined);
+        }
+
+        let mut hash = prev_hash.to_string();
+        let mut height = self.get_block_index(prev_hash)?.get_height();
+        while height % window != 0 {
+            hash = self.get_block(&hash)?.get_prev_hash();
+            height -= 1;
+        }
+
+        let mut state = DeploymentState::Defined;
+        let mut pending = vec![];
+        while height > 0 {
+            let key = (deployment.name.clone(), hash.clone());
+            if let Some(cached) = self.deployment_states.read().unwrap().get(&key) {
+                state = *cached;
+                break;
+            }
+            let mut signals = 0;
+            for _ in 0..window {
+                let header = self.get_block(&hash)?.get_header();
+                if deployment.is_signalled(header.get_version()) {
+                    signals += 1;
+                }
+                hash = header.get_prev_hash();
+            }
+            pending.push((key, height, signals));
+            height -= window;
+        }
+
+        let mut states = self.deployment_states.write().unwrap();
+        for (key, height, signals) in pending.into_iter().rev() {
+            state = self.params.versionbits.next_state(deployment, state, signals, height + 1);
+            states.insert(key, state);
+        }
+        Ok(state)
+    }
+
+    // 软分叉部署在tip之后的下一个区块上的状态，部署不存在时返回None
+    pub fn get_deployment_state(&self, name: &str) -> Option<DeploymentState> {
+        let deployment = self.params.versionbits.get_deployment(name)?;
+        self.deployment_state(deployment, &self.get_tip()).ok()
+    }
+
+    // 软分叉部署是否已经在区块prev_hash之后的区块上生效，新的共识规则据此按高度启用；网络未定义该部署时规则始终生效
+    pub fn is_deployment_active(&self, name: &str, prev_hash: &str) -> Result<bool, BlockchainError> {
+        match self.params.versionbits.get_deployment(name) {
+            Some(deployment) => Ok(self.deployment_state(deployment, prev_hash)? == DeploymentState::Active),
+            None => Ok(true),
+        }
+    }
+
+    /*
+     * 从区块头列表中找到假定有效的区块，沿prev_hash回溯，记录它的祖先区块
+     *     区块hash由区块头计算得出并且包含prev_hash，因此回溯到的区块一定是假定有效区块的祖先，
+     * 不需要先下载区块体。同步区块之前调用，这些区块连接到主链时可以跳过签名验证。
+     */
+    pub fn add_assumed_valid_headers(&self, headers: &[BlockHeader]) {
+        let headers: HashMap<String, &BlockHeader> = headers.iter()
+            .map(|header| (header.hash(), header))
+            .collect();
+        self.record_assumed_valid(|hash| headers.get(hash).map(|header| (*header).clone()));
+    }
+
+    // 从假定有效的区块开始回溯，直到get_header找不到区块头为止
+    fn record_assumed_valid<F>(&self, get_header: F)
+    where
+        F: Fn(&str) -> Option<BlockHeader>
+    {
+        let mut hash = match &self.params.assume_valid {
+            Some(checkpoint) => checkpoint.hash.clone(),
+            None => return,
+        };
+        let mut ancestors = self.assumed_valid.write().unwrap();
+        while let Some(header) = get_header(&hash) {
+            ancestors.insert(hash);
+            hash = header.get_prev_hash();
+        }
+    }
+
+    // 区块是否是假定有效的区块或者它的祖先，侧链上的区块即使高度更低也不是
+    fn is_assumed_valid(&self, hash: &str) -> bool {
+        self.assumed_valid.read().unwrap().contains(hash)
+    }
+
+    // 根据父区块的索引，计算新区块的高度和累计工作量
+    fn next_block_index(&self, block: &Block) -> Result<BlockIndex, BlockchainError> {
+        let prev_hash = block.get_prev_hash();
+        let parent = if prev_hash.is_empty() {
+            // 创世块只能作为空链的第一个区块，且必须与网络参数定义的创世块相同
+            if !self.get_tip().is_empty() || block.get_hash() != self.params.genesis_block().get_hash() {
+                return Err(BlockchainError::GenesisMismatch(block.get_hash()));
+            }
+            BlockIndex::default()
+        } else {
+            self.get_block_index(&prev_hash)?
+        };
+
+        let work = ProofOfWork::work(block.get_header().get_bits());
+        Ok(BlockIndex::new(parent.get_height() + 1, parent.get_chain_work() + work))
+    }
+
+    // 存储区块及其索引，并将其连接到主链，成为新的tip
+    fn set_best_block(&self, block: &Block, index: &BlockIndex) -> Result<(), BlockchainError> {
+        let hash = block.get_hash();
+        self.storage.put_block(&hash, block);
+        self.storage.put_block_index(&hash, index);
+        self.connect_block(block, index.get_height())
+    }
+
+    /*
+     * 将已经存储的区块连接到tip之后，成为新的tip：
+     * 1. 从UTXO集合中删除区块花费的输出，并作为撤销数据保存，断开区块时据此恢复
+     * 2. 将区块中交易产生的输出加入UTXO集合
+     * 3. 记录主链上该高度的区块hash
+     */
+    fn connect_block(&self, block: &Block, height: usize) -> Result<(), BlockchainError> {
+        let hash = block.get_hash();
+        let mut undo = vec![];
+        for tx in block.get_transactions() {
+            if !tx.is_coinbase() {
+                for vin in tx.get_vin() {
+                    let txid = vin.get_txid();
+                    let mut entries = self.storage.get_utxo(&txid)?.unwrap_or_default();
+                    if let Some(idx) = entries.iter().position(|entry| entry.get_vout() == vin.get_vout()) {
+                        undo.push((txid.clone(), entries.remove(idx)));
+                    }
+                    self.write_utxo(&txid, entries)?;
+                }
+            }
+            let entries = tx.get_vout().iter().enumerate()
+                .map(|(idx, out)| UtxoEntry::new(idx, out.clone(), height, tx.is_coinbase()))
+                .collect();
+            self.write_utxo(&tx.get_id(), entries)?;
+        }
+
+        self.storage.put_block_undo(&hash, &undo);
+        self.storage.put_main_chain_hash(height, &hash);
+        self.set_tip(&hash, height);
+        Ok(())
+    }
+
+    // 从主链上断开tip区块：删除区块中交易产生的输出，恢复区块花费的输出，tip回退到父区块
+    fn disconnect_block(&self, block: &Block) -> Result<(), BlockchainError> {
+        let hash = block.get_hash();
+        let height = self.get_block_index(&hash)?.get_height();
+        let undo = self.storage.get_block_undo(&hash)?
+            .ok_or_else(|| BlockchainError::MissingBlockUndo(hash.clone()))?;
+        for tx in block.get_transactions() {
+            self.storage.delete_utxo(&tx.get_id());
+        }
+        self.set_tip(&block.get_prev_hash(), height - 1);
+
+        for (txid, entry) in undo {
+            let mut entries = self.storage.get_utxo(&txid)?.unwrap_or_default();
+            entries.push(entry);
+            entries.sort_by_key(|entry| entry.get_vout());
+            self.write_utxo(&txid, entries)?;
+        }
+        Ok(())
+    }
+
+    // 写入一笔交易的未花费输出，所有输出都已花费时删除该交易
+    fn write_utxo(&self, txid: &str, entries: Vec<UtxoEntry>) -> Result<(), BlockchainError> {
+        if entries.is_empty() {
+            self.storage.delete_utxo(txid);
+            return Ok(());
+        }
+        self.storage.write_utxo(txid, entries)
+    }
+
+    // 清空UTXO集合，并根据当前主链重新生成
+    pub fn reindex_utxos(&self) -> Result<(), BlockchainError> {
+        self.storage.clear_utxo_set();
+        for (txid, outs) in self.find_utxo() {
+            self.storage.write_utxo(&txid, outs)?;
+        }
+        Ok(())
+    }
+
+    // 在UTXO集合中查找一个未花费的交易输出，UTXO集合始终与主链的tip一致
+    pub fn get_utxo(&self, txid: &str, vout: usize) -> Option<UtxoEntry> {
+        self.storage.get_utxo(txid).ok().flatten()?
+            .into_iter()
+            .find(|entry| entry.get_vout() == vout)
+    }
+
+    // 主链上高度为height的区块hash
+    fn get_main_chain_hash(&self, height: usize) -> Result<String, BlockchainError> {
+        self.storage.get_main_chain_hash(height)?
+            .filter(|_| height <= self.get_height())
+            .ok_or_else(|| BlockchainError::UnknownBlock(height.to_string()))
+    }
+
+    // 切换tip到一个已经存储的区块
+    fn set_tip(&self, hash: &str, height: usize) {
+        self.storage.set_tip(hash, height);
+        self.height.store(height, Ordering::Relaxed);
+        *self.tip.write().unwrap() = hash.to_string();
+    }
+
+    // 根据hash查找已经存储的区块，包括侧链上的区块
+    pub fn find_block(&self, hash: &str) -> Option<Block> {
+        self.storage.get_block(hash).ok().flatten()
+    }
+
+    fn get_block(&self, hash: &str) -> Result<Block, BlockchainError> {
+        self.storage.get_block(hash)?
+            .ok_or_else(|| BlockchainError::UnknownParent(hash.to_string()))
+    }
+
+    fn get_block_index(&self, hash: &str) -> Result<BlockIndex, BlockchainError> {
+        self.storage.get_block_index(hash)?
+            .ok_or_else(|| BlockchainError::UnknownParent(hash.to_string()))
+    }
+
     /* 遍历所有区块，栈结构，使用pop方法从第一个区块开始遍历 */
     fn get_blocks(&self) -> Vec<Block> {
         let mut blocks: Vec<Block> = vec![];
@@ -116,50 +801,38 @@ impl<T: KVStorage> Blockchain<T> {
         ordered_blocks
     }
 
-    /* 遍历区块链，找到所有维护费交易UTXO  */
-    pub fn find_utxo(&self) -> HashMap<String, Vec<TxOutput>> {
+    /* 遍历区块链，找到所有未花费的交易输出，以及它们所在区块的高度 */
+    pub fn find_utxo(&self) -> HashMap<String, Vec<UtxoEntry>> {
         let mut utxo = HashMap::new();
-        let mut spent_txos = HashMap::new();
+        let mut spent_txos: HashMap<String, Vec<usize>> = HashMap::new();
 
         // 遍历区块链，必须倒序！
+        let mut height = self.get_height();
         let mut iter = BlockchainIterator::new(self.get_tip(), self.storage.clone());
-        loop {
-            let block = iter.next();
-            if block.is_none() {
-                break;
-            }
-
-            // 遍历当前区块的所有交易
-            for tx in block.unwrap().get_transactions() {
-                // 遍历当前交易的所有输出
-                for (idx, tx_out) in tx.get_vout().iter().enumerate() {
-                    // 如果已花费账户map中已经记录了transaction id，则需要
-                    // 判断当前输出是否已经被花费
-                    if let Some(spent_outs) = spent_txos.get(&tx.get_id()) {
-                        for out in spent_outs {
-                            if idx.eq(out) {
-                                break;
-                            }
-                        
-                            utxo.entry(tx.get_id())
-                                .and_modify(|v: &mut Vec<TxOutput>| v.push(tx_out.clone()))
-                                .or_insert(vec![tx_out.clone()]);
-                        }
-                    } else {
-                        utxo.entry(tx.get_id())
-                            .and_modify(|v: &mut Vec<TxOutput>| v.push(tx_out.clone()))
-                            .or_insert(vec![tx_out.clone()]);
-                    }
+        while let Some(block) = iter.next() {
+            // 遍历当前区块的所有交易，区块内后面的交易可能花费前面交易的输出，因此也要倒序
+            for tx in block.get_transactions().iter().rev() {
+                // 遍历当前交易的所有输出，跳过已经被之后的交易花费的输出
+                let spent_outs = spent_txos.get(&tx.get_id());
+                let entries: Vec<UtxoEntry> = tx.get_vout().iter().enumerate()
+                    .filter(|(idx, _)| !spent_outs.is_some_and(|outs| outs.contains(idx)))
+                    .map(|(idx, out)| UtxoEntry::new(idx, out.clone(), height, tx.is_coinbase()))
+                    .collect();
+                if !entries.is_empty() {
+                    utxo.insert(tx.get_id(), entries);
                 }
 
                 // 将当前交易的inputs加入到已消费账户中，一个TxInput必然是之前某个
                 // 交易的TxOutput，用(txid, vout)唯一定义
-                for tx_in in tx.get_vin() {
-                    spent_txos.entry(tx_in.get_txid())
-                        .and_modify(|v: &mut Vec<usize>| v.push(tx_in.get_vout()))
-                        .or_insert(vec![tx_in.get_vout()]);
+                if !tx.is_coinbase() {
+                    for tx_in in tx.get_vin() {
+                        spent_txos.entry(tx_in.get_txid())
+                            .or_default()
+                            .push(tx_in.get_vout());
+                    }
                 }
             }
+            height = height.saturating_sub(1);
         }
 
         utxo
@@ -183,6 +856,67 @@ impl<T: KVStorage> Blockchain<T> {
         None
     }
 
+    // 在主链上查找交易所在的区块，返回区块头和该交易的Merkle包含证明
+    pub fn find_merkle_proof(&self, txid: &str) -> Option<(BlockHeader, MerkleProof)> {
+        let mut iter = BlockchainIterator::new(self.get_tip(), self.storage.clone());
+        while let Some(block) = iter.next() {
+            if let Some(proof) = block.get_merkle_proof(txid) {
+                return Some((block.get_header(), proof));
+            }
+        }
+        None
+    }
+
+    // 在主链上查找已经公开的哈希时间锁合约原像，即某个交易输入中SHA256哈希等于hash_lock的数据
+    pub fn find_preimage(&self, hash_lock: &[u8]) -> Option<Vec<u8>> {
+        let mut iter = BlockchainIterator::new(self.get_tip(), self.storage.clone());
+        while let Some(block) = iter.next() {
+            for tx in block.get_transactions().iter().filter(|tx| !tx.is_coinbase()) {
+                if let Some(preimage) = tx.get_vin().iter().find_map(|vin| vin.get_script_sig().find_preimage(hash_lock)) {
+                    return Some(preimage.to_vec());
+                }
+            }
+        }
+        None
+    }
+
+    // 查找主链上所有支付给pub_key_hash的交易，以及各自的区块头和Merkle包含证明
+    pub fn find_payments(&self, pub_key_hash: &[u8]) -> Vec<(Transaction, BlockHeader, MerkleProof)> {
+        let mut payments = vec![];
+        let mut iter = BlockchainIterator::new(self.get_tip(), self.storage.clone());
+        while let Some(block) = iter.next() {
+            for tx in block.get_transactions() {
+                if tx.get_vout().iter().any(|out| out.is_locked(pub_key_hash)) {
+                    if let Some(proof) = block.get_merkle_proof(&tx.get_id()) {
+                        payments.push((tx, block.get_header(), proof));
+                    }
+                }
+            }
+        }
+        payments
+    }
+
+    /*
+     * 根据轻节点的区块定位器找到分叉点，返回主链上分叉点之后的至多max个区块头
+     *     分叉点是定位器中位于主链上的最高区块，通过主链的高度索引按高度读取之后的区块，
+     * 不需要遍历整条区块链；定位器中没有主链上的区块时从创世块开始返回。
+     */
+    pub fn get_headers_after(&self, locator: &[String], max: usize) -> Vec<BlockHeader> {
+        let fork_height = locator.iter()
+            .filter_map(|hash| {
+                let height = self.storage.get_block_index(hash).ok().flatten()?.get_height();
+                (self.get_main_chain_hash(height).ok()? == *hash).then_some(height)
+            })
+            .max()
+            .unwrap_or(0);
+        let end = self.get_height().min(fork_height.saturating_add(max));
+        ((fork_height + 1)..=end)
+            .map_while(|height| self.get_main_chain_hash(height).ok())
+            .map_while(|hash| self.find_block(&hash))
+            .map(|block| block.get_header())
+            .collect()
+    }
+
     /* 打印区块链详细信息 */
     pub fn blocks_info(&self) {
         let mut blocks = self.get_blocks();
@@ -191,6 +925,28 @@ impl<T: KVStorage> Blockchain<T> {
         }
     }
 
+    pub fn get_params(&self) -> &ChainParams {
+        &self.params
+    }
+
+    pub fn get_consensus(&self) -> Arc<dyn Consensus> {
+        self.consensus.clone()
+    }
+
+    pub fn get_time_data(&self) -> &TimeData {
+        &self.time_data
+    }
+
+    // 高度为height的区块允许铸造的挖矿奖励
+    pub fn get_subsidy(&self, height: usize) -> Amount {
+        self.params.subsidy.subsidy(height)
+    }
+
+    // 截至高度height，按发行计划铸造的代币总量
+    pub fn circulating_supply(&self, height: usize) -> Amount {
+        self.params.subsidy.circulating_supply(height)
+    }
+
     pub fn get_tip(&self) -> String {
         self.tip.read().unwrap().to_string()
     }
@@ -227,3 +983,101 @@ impl<T: KVStorage> BlockchainIterator<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Wallet;
+
+    // 每个测试使用独立的数据库目录，dev网络使用InstantSeal，不需要计算工作量
+    fn new_blockchain(name: &str) -> Blockchain {
+        let path = std::env::temp_dir().join(format!("hungrytiger-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let mut bc = Blockchain::with_params(Arc::new(RocksDb::new(path)), ChainParams::dev());
+        bc.create_genesis_block().unwrap();
+        bc
+    }
+
+    // 挖出两个奖励发给wallet的区块，等coinbase成熟之后，返回分别花费它们的两笔交易
+    fn mature_spends(bc: &mut Blockchain, wallet: &Wallet) -> Vec<Transaction> {
+        let addr = wallet.get_address(bc.get_params().address_version);
+        let coinbases: Vec<Transaction> = (0..2)
+            .map(|_| bc.mining(&addr, &[]).unwrap().get_transactions()[0].clone())
+            .collect();
+        for _ in 0..bc.get_params().coinbase_maturity {
+            bc.mining(&addr, &[]).unwrap();
+        }
+        coinbases.iter().map(|coinbase| Transaction::new_signed_spend(coinbase, 0, wallet, &addr)).collect()
+    }
+
+    // 在tip之上封装包含txs的区块
+    fn sealed_block(bc: &Blockchain, miner_addr: &str, txs: &[Transaction]) -> Block {
+        let mut block = bc.new_block_template(miner_addr, txs, bc.params.max_block_size, &[]).unwrap();
+        assert!(bc.consensus.seal(&mut block, &AtomicBool::new(false)));
+        block
+    }
+
+    // 重复最后一笔交易，区块头与原区块相同
+    fn duplicate_tail(bc: &Blockchain, block: &Block) -> Block {
+        let mut txs = block.get_transactions();
+        txs.push(txs.last().unwrap().clone());
+        let header = block.get_header();
+        let mut forged = Block::new_template(&txs, &header.get_prev_hash(), header.get_bits());
+        forged.set_version(header.get_version());
+        forged.set_timestamp(header.get_timestamp());
+        assert!(bc.consensus.seal(&mut forged, &AtomicBool::new(false)));
+        forged
+    }
+
+    #[test]
+    fn duplicate_tail_is_rejected() {
+        let mut bc = new_blockchain("duplicate-tail");
+        let wallet = Wallet::new();
+        let addr = wallet.get_address(bc.get_params().address_version);
+        let txs = mature_spends(&mut bc, &wallet);
+
+        let block = sealed_block(&bc, &addr, &txs);
+        let forged = duplicate_tail(&bc, &block);
+        assert_eq!(block.get_transactions().len(), 3);
+        assert_eq!(forged.get_hash(), block.get_hash());
+
+        assert!(matches!(bc.check_block(&forged), Err(BlockchainError::DuplicateTransaction(_))));
+        assert!(bc.check_block(&block).is_ok());
+    }
+
+    // 伪造的副本先到达，不能使正常的区块被标记为无效
+    #[test]
+    fn malleated_copy_does_not_invalidate_block() {
+        let mut bc = new_blockchain("malleated-copy");
+        let wallet = Wallet::new();
+        let addr = wallet.get_address(bc.get_params().address_version);
+        let txs = mature_spends(&mut bc, &wallet);
+
+        let block = sealed_block(&bc, &addr, &txs);
+        let forged = duplicate_tail(&bc, &block);
+        assert!(bc.add_block(forged).is_err());
+        assert!(!bc.storage.is_invalid_block(&block.get_hash()).unwrap());
+
+        assert!(matches!(bc.add_block(block.clone()), Ok(ChainUpdate::Extended(_))));
+        assert_eq!(bc.get_tip(), block.get_hash());
+    }
+
+    // 侧链上伪造的副本不会被存储，正常的区块到达时存储的是正常的交易
+    #[test]
+    fn malleated_side_block_is_not_stored() {
+        let mut bc = new_blockchain("malleated-side");
+        let wallet = Wallet::new();
+        let addr = wallet.get_address(bc.get_params().address_version);
+        let txs = mature_spends(&mut bc, &wallet);
+
+        let block = sealed_block(&bc, &addr, &txs);
+        let forged = duplicate_tail(&bc, &block);
+        // 另一个区块先接在tip之后，block成为侧链上的区块
+        bc.mining(&addr, &[]).unwrap();
+
+        assert!(bc.add_block(forged).is_err());
+        assert!(bc.storage.get_block(&block.get_hash()).unwrap().is_none());
+        assert!(matches!(bc.add_block(block.clone()), Ok(ChainUpdate::SideChain)));
+        assert_eq!(bc.get_block(&block.get_hash()).unwrap().get_transactions().len(), 3);
+    }
+}
@@ -0,0 +1,295 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{Amount, Block, Script, ConsensusParams, Deployment, DEPLOYMENT_CSV, DifficultyParams, SubsidyParams, VersionBitsParams, COINBASE_MATURITY, validate_address, base58_decode, address_to_pub_key_hash, error::BlockchainError};
+
+/* 区块链网络类型 */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Network {
+    Main,       // 主网
+    Test,       // 测试网
+    Regtest,    // 本地回归测试网络，难度最低且不调整，适合在单机上快速出块
+    Dev,        // 本地开发网络，区块即时封装，不消耗算力
+    Private,    // 私有网络，由一组验证者轮流签名出块
+}
+
+/*
+ * 创世块的内容
+ *     同一个网络中所有节点必须生成完全相同的创世块，因此时间戳固定，
+ * 并且只使用单线程从0开始搜索nonce。
+ */
+#[derive(Debug, Clone)]
+pub struct GenesisParams {
+    pub timestamp: i64,         // 创世块的时间戳，单位为秒
+    pub address: String,        // 创世块奖励的接收地址
+    pub coinbase_data: Vec<u8>, // 写入创世块coinbase交易的数据
+}
+
+/*
+ * 检查点：硬编码在网络参数中的已知区块
+ *     主链在该高度上的区块必须是指定的区块，检查点之前的分叉会被拒绝。
+ */
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub height: usize,  // 区块高度
+    pub hash: String,   // 区块hash
+}
+
+/*
+ * 区块链网络参数
+ *     汇总一个网络的全部共识参数和网络配置，Blockchain、Node和Wallet都从这里读取，
+ * 不同网络的节点使用不同的gossipsub topic，互不干扰。
+ */
+#[derive(Debug, Clone)]
+pub struct ChainParams {
+    pub network: Network,               // 网络类型
+    pub genesis: GenesisParams,         // 创世块的内容
+    pub consensus: ConsensusParams,     // 共识机制
+    pub difficulty: DifficultyParams,   // 难度调整参数
+    pub subsidy: SubsidyParams,         // 挖矿奖励参数
+    pub coinbase_maturity: usize,       // coinbase输出至少经过多少个区块才能花费
+    pub versionbits: VersionBitsParams, // 软分叉部署及其信号参数
+    pub max_block_size: usize,          // 区块序列化之后的最大字节数
+    pub max_block_sigops: usize,        // 一个区块中所有交易的签名操作数量上限
+    pub checkpoints: Vec<Checkpoint>,   // 检查点
+    pub assume_valid: Option<Checkpoint>,   // 假定有效的区块，该区块及其祖先区块中的交易不再验证签名
+    pub address_version: u8,            // 地址的版本前缀
+    pub script_address_version: u8,     // 脚本（P2SH）地址的版本前缀
+    pub block_topic: String,            // 广播区块的topic
+    pub tranx_topic: String,            // 广播交易的topic
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        Self::main()
+    }
+}
+
+impl ChainParams {
+    // 主网参数
+    pub fn main() -> Self {
+        Self {
+            network: Network::Main,
+            genesis: GenesisParams {
+                timestamp: 1_640_995_200,
+                address: String::from("1GMXdoTqE4wfG1kdxDDkYz4qNr7x3dMG8b"),
+                coinbase_data: b"hungrytiger genesis".to_vec(),
+            },
+            consensus: ConsensusParams::ProofOfWork,
+            difficulty: DifficultyParams::default(),
+            subsidy: SubsidyParams::default(),
+            coinbase_maturity: COINBASE_MATURITY,
+            versionbits: VersionBitsParams {
+                window: 100,
+                threshold: 95,
+                deployments: vec![],
+            },
+            max_block_size: 100_000,
+            max_block_sigops: 2_000,
+            // 新版本发布时将检查点和假定有效的区块推进到当时主链上足够深的区块
+            checkpoints: vec![Self::main_genesis()],
+            assume_valid: Some(Self::main_genesis()),
+            address_version: 0x00,
+            script_address_version: 0x05,
+            block_topic: String::from("blocks"),
+            tranx_topic: String::from("tranxs"),
+        }
+    }
+
+    // 测试网参数：共识规则与主网相同，地址前缀和topic不同
+    pub fn test() -> Self {
+        Self {
+            network: Network::Test,
+            genesis: GenesisParams {
+                timestamp: 1_640_995_200,
+                address: String::from("mvsUvrYp36Nv38EFfnC8NuHAEqiexg4LDf"),
+                coinbase_data: b"hungrytiger testnet genesis".to_vec(),
+            },
+            versionbits: VersionBitsParams {
+                deployments: vec![Self::test_dummy(), Self::csv()],
+                ..VersionBitsParams::default()
+            },
+            checkpoints: vec![Self::test_genesis()],
+            assume_valid: Some(Self::test_genesis()),
+            address_version: 0x6f,
+            script_address_version: 0xc4,
+            block_topic: String::from("test-blocks"),
+            tranx_topic: String::from("test-tranxs"),
+            ..Self::main()
+        }
+    }
+
+    // 回归测试网参数：难度最低且不调整，奖励减半更快
+    pub fn regtest() -> Self {
+        Self {
+            network: Network::Regtest,
+            genesis: GenesisParams {
+                timestamp: 1_640_995_200,
+                address: String::from("mvsUvrYp36Nv38EFfnC8NuHAEqiexg4LDf"),
+                coinbase_data: b"hungrytiger regtest genesis".to_vec(),
+            },
+            difficulty: DifficultyParams {
+                initial_bits: 0x207f_ffff,
+                pow_limit: 0x207f_ffff,
+                retarget_interval: 0,
+                target_block_time: 1,
+            },
+            subsidy: SubsidyParams {
+                initial_subsidy: Amount::from_coins(20),
+                halving_interval: 150,
+                max_supply: Amount::from_coins(6000),
+            },
+            versionbits: VersionBitsParams {
+                deployments: vec![Self::test_dummy()],
+                ..VersionBitsParams::default()
+            },
+            // 回归测试网用于测试交易验证，所有区块都验证签名
+            checkpoints: vec![Checkpoint {
+                height: 1,
+                hash: String::from("636cfc333a2f84f33ae21f38e3f2fa127859a7f297e0b8af228784970a108419"),
+            }],
+            assume_valid: None,
+            address_version: 0x6f,
+            script_address_version: 0xc4,
+            block_topic: String::from("regtest-blocks"),
+            tranx_topic: String::from("regtest-tranxs"),
+            ..Self::main()
+        }
+    }
+
+    // 本地开发网络参数：与回归测试网相同，但区块即时封装
+    pub fn dev() -> Self {
+        Self {
+            network: Network::Dev,
+            genesis: GenesisParams {
+                timestamp: 1_640_995_200,
+                address: String::from("mvsUvrYp36Nv38EFfnC8NuHAEqiexg4LDf"),
+                coinbase_data: b"hungrytiger dev genesis".to_vec(),
+            },
+            consensus: ConsensusParams::InstantSeal,
+            checkpoints: vec![Checkpoint {
+                height: 1,
+                hash: String::from("77573779b883717e5eead4679472463da97af5bd322ef2a1c84b4fc7e7a265cc"),
+            }],
+            block_topic: String::from("dev-blocks"),
+            tranx_topic: String::from("dev-tranxs"),
+            ..Self::regtest()
+        }
+    }
+
+    // 私有网络参数：validators是允许出块的验证者地址，创世块奖励发给第一个验证者
+    pub fn private(validators: Vec<String>) -> Self {
+        Self {
+            network: Network::Private,
+            genesis: GenesisParams {
+                timestamp: 1_640_995_200,
+                address: validators.first().cloned().unwrap_or_default(),
+                coinbase_data: b"hungrytiger private genesis".to_vec(),
+            },
+            consensus: ConsensusParams::ProofOfAuthority { validators },
+            // 创世块由验证者决定，没有预先确定的检查点
+            checkpoints: vec![],
+            block_topic: String::from("private-blocks"),
+            tranx_topic: String::from("private-tranxs"),
+            ..Self::regtest()
+        }
+    }
+
+    // 主网的创世块
+    fn main_genesis() -> Checkpoint {
+        Checkpoint {
+            height: 1,
+            hash: String::from("34f48bb6dcc136892c75d418e88014ff18773a0967b46f818dec7b0ad2357415"),
+        }
+    }
+
+    // 测试网的创世块
+    fn test_genesis() -> Checkpoint {
+        Checkpoint {
+            height: 1,
+            hash: String::from("1f7b680a96295b1e95f49d8cb13285c1e8e249ec3b217ae91cbcfac86c7c798f"),
+        }
+    }
+
+    // 测试网络上用于验证版本位信号流程的部署，不对应任何规则变化
+    fn test_dummy() -> Deployment {
+        Deployment {
+            name: String::from("testdummy"),
+            bit: 28,
+            start_height: 1,
+            timeout_height: usize::MAX,
+        }
+    }
+
+    // 测试网上通过版本位信号激活相对时间锁，其他网络未定义该部署，从创世块开始检查相对时间锁
+    fn csv() -> Deployment {
+        Deployment {
+            name: String::from(DEPLOYMENT_CSV),
+            bit: 0,
+            start_height: 1,
+            timeout_height: usize::MAX,
+        }
+    }
+
+    // 根据网络名称获取参数：main、test、regtest或dev，私有网络需要指定验证者，使用private构造
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "main" => Some(Self::main()),
+            "test" => Some(Self::test()),
+            "regtest" => Some(Self::regtest()),
+            "dev" => Some(Self::dev()),
+            _ => None,
+        }
+    }
+
+    // 高度为height的检查点区块hash，假定有效的区块同样作为检查点
+    pub fn get_checkpoint(&self, height: usize) -> Option<&str> {
+        self.checkpoints.iter()
+            .chain(self.assume_valid.iter())
+            .find(|checkpoint| checkpoint.height == height)
+            .map(|checkpoint| checkpoint.hash.as_str())
+    }
+
+    // 不超过max_height的最高检查点的高度，没有检查点时返回0
+    pub fn last_checkpoint_height(&self, max_height: usize) -> usize {
+        self.checkpoints.iter()
+            .chain(self.assume_valid.iter())
+            .map(|checkpoint| checkpoint.height)
+            .filter(|height| *height <= max_height)
+            .max()
+            .unwrap_or_default()
+    }
+
+    // 当前网络的创世块，同一网络的所有节点生成的创世块相同
+    pub fn genesis_block(&self) -> Block {
+        Block::create_genesis(&self.genesis, self.difficulty.initial_bits, self.subsidy.subsidy(1))
+    }
+
+    // 启动节点前检查网络参数：权威证明的验证者必须是当前网络的公钥地址，否则无法验证区块的封装
+    pub fn validate(&self) -> Result<(), BlockchainError> {
+        if let ConsensusParams::ProofOfAuthority { validators } = &self.consensus {
+            if let Some(address) = validators.iter()
+                .find(|address| !self.is_valid_address(address) || base58_decode(address)[0] != self.address_version) {
+                return Err(BlockchainError::InvalidAddress(address.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    // 校验地址格式正确，且属于当前网络，公钥地址和脚本地址都有效
+    pub fn is_valid_address(&self, address: &str) -> bool {
+        validate_address(address) && [self.address_version, self.script_address_version].contains(&base58_decode(address)[0])
+    }
+
+    // 支付给地址的锁定脚本：公钥地址使用P2PKH，脚本地址使用P2SH
+    pub fn address_to_script(&self, address: &str) -> Result<Script, BlockchainError> {
+        if !self.is_valid_address(address) {
+            return Err(BlockchainError::InvalidAddress(address.to_string()));
+        }
+        let hash = address_to_pub_key_hash(address);
+        if base58_decode(address)[0] == self.script_address_version {
+            Ok(Script::new_p2sh(&hash))
+        } else {
+            Ok(Script::new_p2pkh(&hash))
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::sync::{Arc, atomic::AtomicBool};
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    Block, BlockHeader, ChainParams, DifficultyParams, Miner, ProofOfWork, Wallet,
+    address_to_pub_key_hash, hash_pub_key, error::BlockchainError,
+    utils::{serialize, deserialize, ecdsa_p256_sha256_sign_digest, ecdsa_p256_sha256_sign_verify},
+};
+
+/* 网络使用的共识机制 */
+#[derive(Debug, Clone)]
+pub enum ConsensusParams {
+    ProofOfWork,                                    // 工作量证明，按难度搜索nonce
+    ProofOfAuthority { validators: Vec<String> },   // 权威证明，区块由验证者地址对应的私钥签名
+    InstantSeal,                                    // 开发用，区块无需任何计算立即封装
+}
+
+/*
+ * 共识引擎
+ *     负责封装区块（seal）、验证区块头的封装（verify_seal），以及计算下一个区块的难度。
+ * Blockchain和HeaderChain只依赖该接口，不关心具体的共识机制。
+ */
+pub trait Consensus: Send + Sync {
+    // 封装区块，成功则设置区块的hash并返回true；被取消或无法封装则返回false
+    fn seal(&self, block: &mut Block, cancel: &AtomicBool) -> bool;
+
+    // 验证区块头的封装是否有效
+    fn verify_seal(&self, header: &BlockHeader) -> Result<(), BlockchainError>;
+
+    // 计算parent之后下一个区块的难度，get_header根据区块hash查找祖先区块头
+    fn next_bits(&self, parent: &BlockHeader, parent_height: usize, get_header: &dyn Fn(&str) -> Option<BlockHeader>) -> u32;
+
+    // 最近一次封装的算力，单位为hash/s，不需要计算的共识机制返回0
+    fn get_hash_rate(&self) -> u64 {
+        0
+    }
+
+    // 本节点能否封装区块，不能封装的节点只验证区块，不应该发起挖矿
+    fn can_seal(&self) -> bool {
+        true
+    }
+}
+
+/*
+ * 根据网络参数创建共识引擎
+ * signer: 权威证明中本节点用于签名区块的验证者钱包，为None时只能验证区块，不能封装区块
+ */
+pub fn create_consensus(params: &ChainParams, signer: Option<Wallet>) -> Arc<dyn Consensus> {
+    match &params.consensus {
+        ConsensusParams::ProofOfWork => Arc::new(PowConsensus::new(params.difficulty.clone(), Miner::default())),
+        ConsensusParams::ProofOfAuthority { validators } => Arc::new(PoaConsensus::new(validators.clone(), signer)),
+        ConsensusParams::InstantSeal => Arc::new(InstantSeal),
+    }
+}
+
+/* 工作量证明：由多线程挖矿引擎搜索nonce，难度按DifficultyParams调整 */
+pub struct PowConsensus {
+    difficulty: DifficultyParams,   // 难度调整参数
+    miner: Miner,                   // 多线程挖矿引擎
+}
+
+impl PowConsensus {
+    pub fn new(difficulty: DifficultyParams, miner: Miner) -> Self {
+        Self { difficulty, miner }
+    }
+}
+
+impl Consensus for PowConsensus {
+    fn seal(&self, block: &mut Block, cancel: &AtomicBool) -> bool {
+        self.miner.mine(block, cancel)
+    }
+
+    fn verify_seal(&self, header: &BlockHeader) -> Result<(), BlockchainError> {
+        ProofOfWork::new(header.get_bits()).validate_header(header)
+    }
+
+    fn next_bits(&self, parent: &BlockHeader, parent_height: usize, get_header: &dyn Fn(&str) -> Option<BlockHeader>) -> u32 {
+        self.difficulty.next_bits(parent, parent_height, get_header)
+    }
+
+    fn get_hash_rate(&self) -> u64 {
+        self.miner.get_hash_rate()
+    }
+}
+
+/* 权威证明区块头中的封装：验证者的公钥，以及对区块头seal_hash的签名 */
+#[derive(Serialize, Deserialize)]
+struct AuthoritySeal {
+    public_key: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+/*
+ * 权威证明
+ *     只有验证者集合中的地址可以出块，区块头的seal字段保存验证者的公钥和签名。
+ * 难度保持不变，每个区块的工作量相同，累计工作量最大的链即为最长链。
+ */
+pub struct PoaConsensus {
+    validators: Vec<Vec<u8>>,   // 验证者地址对应的public key hash
+    signer: Option<Wallet>,     // 本节点的验证者钱包
+}
+
+impl PoaConsensus {
+    pub fn new(validators: Vec<String>, signer: Option<Wallet>) -> Self {
+        Self {
+            validators: validators.iter().map(|address| address_to_pub_key_hash(address)).collect(),
+            signer,
+        }
+    }
+
+    fn is_validator(&self, public_key: &[u8]) -> bool {
+        self.validators.contains(&hash_pub_key(public_key))
+    }
+}
+
+impl Consensus for PoaConsensus {
+    fn seal(&self, block: &mut Block, _cancel: &AtomicBool) -> bool {
+        let signer = match &self.signer {
+            Some(signer) if self.is_validator(signer.get_public_key()) => signer,
+            _ => return false,
+        };
+
+        let message = block.get_header().seal_hash();
+        let seal = AuthoritySeal {
+            public_key: signer.get_public_key().to_vec(),
+            signature: ecdsa_p256_sha256_sign_digest(signer.get_private_key(), message.as_bytes()),
+        };
+        match serialize(&seal) {
+            Ok(seal) => block.set_seal(seal),
+            Err(_) => return false,
+        }
+        block.set_hash(block.get_header().hash());
+        true
+    }
+
+    fn verify_seal(&self, header: &BlockHeader) -> Result<(), BlockchainError> {
+        let seal: AuthoritySeal = deserialize(header.get_seal())
+            .map_err(|_| BlockchainError::InvalidSeal(header.hash()))?;
+        if !self.is_validator(&seal.public_key)
+            || !ecdsa_p256_sha256_sign_verify(&seal.public_key, &seal.signature, header.seal_hash().as_bytes()) {
+            return Err(BlockchainError::InvalidSeal(header.hash()));
+        }
+        Ok(())
+    }
+
+    fn next_bits(&self, parent: &BlockHeader, _parent_height: usize, _get_header: &dyn Fn(&str) -> Option<BlockHeader>) -> u32 {
+        parent.get_bits()
+    }
+
+    // 只有持有验证者私钥的节点才能出块
+    fn can_seal(&self) -> bool {
+        matches!(&self.signer, Some(signer) if self.is_validator(signer.get_public_key()))
+    }
+}
+
+/* 开发用的即时封装：不做任何计算，适合本地开发和测试，不能用于公开网络 */
+pub struct InstantSeal;
+
+impl Consensus for InstantSeal {
+    fn seal(&self, block: &mut Block, _cancel: &AtomicBool) -> bool {
+        block.set_hash(block.get_header().hash());
+        true
+    }
+
+    fn verify_seal(&self, _header: &BlockHeader) -> Result<(), BlockchainError> {
+        Ok(())
+    }
+
+    fn next_bits(&self, parent: &BlockHeader, _parent_height: usize, _get_header: &dyn Fn(&str) -> Option<BlockHeader>) -> u32 {
+        parent.get_bits()
+    }
+}
@@ -0,0 +1,127 @@
+use bigint::U256;
+
+use crate::{BlockHeader, ProofOfWork};
+
+// 每次难度调整，target最多变为原来的4倍或者1/4
+const MAX_ADJUST_FACTOR: i64 = 4;
+
+/*
+ * 难度调整参数
+ *     每隔retarget_interval个区块，根据这段时间内的实际出块时间按比例调整target：
+ * new_target = old_target * 实际出块时间 / 期望出块时间，且不能超过pow_limit。
+ */
+#[derive(Debug, Clone)]
+pub struct DifficultyParams {
+    pub initial_bits: u32,          // 创世块的难度，target的紧凑编码
+    pub pow_limit: u32,             // 允许的最低难度，即最大的target
+    pub retarget_interval: usize,   // 每隔多少个区块调整一次难度
+    pub target_block_time: i64,     // 期望的出块间隔，单位为秒
+}
+
+impl Default for DifficultyParams {
+    fn default() -> Self {
+        Self {
+            initial_bits: 0x2040_0000,
+            pow_limit: 0x207f_ffff,
+            retarget_interval: 10,
+            target_block_time: 30,
+        }
+    }
+}
+
+impl DifficultyParams {
+    /*
+     * 计算parent之后下一个区块的难度
+     * parent_height: 父区块的高度，创世块为1
+     * get_header: 根据区块hash查找区块头，用于回溯祖先区块的时间戳
+     */
+    pub fn next_bits<F>(&self, parent: &BlockHeader, parent_height: usize, get_header: F) -> u32
+    where
+        F: Fn(&str) -> Option<BlockHeader>
+    {
+        let bits = parent.get_bits();
+        if self.retarget_interval == 0 || !parent_height.is_multiple_of(self.retarget_interval) {
+            return bits;
+        }
+
+        // 回溯到调整周期的第一个区块
+        let mut first = parent.clone();
+        let mut first_height = parent_height;
+        while first_height > 1 && parent_height - first_height < self.retarget_interval {
+            match get_header(&first.get_prev_hash()) {
+                Some(header) => first = header,
+                None => return bits,
+            }
+            first_height -= 1;
+        }
+
+        let blocks = (parent_height - first_height) as i64;
+        if blocks == 0 {
+            return bits;
+        }
+        let expected = (blocks * self.target_block_time).max(1);
+        let actual = (parent.get_timestamp() - first.get_timestamp())
+            .clamp(expected / MAX_ADJUST_FACTOR, expected * MAX_ADJUST_FACTOR)
+            .max(1);
+
+        let old_target = match ProofOfWork::compact_to_target(bits) {
+            Some(target) => target,
+            None => return bits,
+        };
+        let (actual, expected) = (U256::from(actual as u64), U256::from(expected as u64));
+        // 先乘后除以保留精度，乘法溢出时先除后乘，仍然溢出说明新的target超过了pow_limit
+        let new_target = match old_target.overflowing_mul(actual) {
+            (product, false) => product / expected,
+            (_, true) => match (old_target / expected).overflowing_mul(actual) {
+                (product, false) => product,
+                (_, true) => self.pow_limit_target(),
+            },
+        };
+        let new_target = new_target.min(self.pow_limit_target()).max(U256::one());
+        ProofOfWork::target_to_compact(new_target)
+    }
+
+    // 允许的最大target
+    fn pow_limit_target(&self) -> U256 {
+        ProofOfWork::compact_to_target(self.pow_limit).unwrap_or_default()
+    }
+
+    // 判断bits是合法的紧凑编码，且target在允许的难度范围内
+    pub fn is_valid_bits(&self, bits: u32) -> bool {
+        ProofOfWork::compact_to_target(bits)
+            .is_some_and(|target| !target.is_zero() && target <= self.pow_limit_target())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::Block;
+
+    // 从pow_limit开始，出块时间远超预期，target放大4倍会超过U256的范围
+    #[test]
+    fn slow_retarget_from_pow_limit() {
+        let params = DifficultyParams {
+            initial_bits: 0x2100_ffff,
+            pow_limit: 0x2100_ffff,
+            ..DifficultyParams::default()
+        };
+
+        let mut headers = HashMap::new();
+        let mut prev_hash = String::new();
+        let mut parent = None;
+        for height in 0..params.retarget_interval {
+            let mut block = Block::new_template(&[], &prev_hash, params.pow_limit);
+            block.set_timestamp(height as i64 * params.target_block_time * 10);
+            let header = block.get_header();
+            prev_hash = header.hash();
+            headers.insert(prev_hash.clone(), header.clone());
+            parent = Some(header);
+        }
+
+        let bits = params.next_bits(&parent.unwrap(), params.retarget_interval, |hash| headers.get(hash).cloned());
+        assert_eq!(bits, params.pow_limit);
+    }
+}
@@ -0,0 +1,172 @@
+use std::sync::{Arc, RwLock, atomic::{AtomicUsize, Ordering}};
+use chrono::Utc;
+
+use crate::{BlockHeader, BlockIndex, ChainParams, Consensus, create_consensus, check_block_time, median_time_past, KVStorage, MerkleProof, ProofOfWork, RocksDb, error::BlockchainError};
+
+/*
+ * 区块头链，供轻节点（SPV）使用
+ *     只下载并验证区块头：prev_hash的链接关系、难度和工作量证明。
+ * 不存储交易，也不维护UTXO集合，通过Merkle证明验证交易属于某个区块。
+ */
+pub struct HeaderChain<T = RocksDb> {
+    storage: Arc<T>,            // 区块头的存储
+    tip: RwLock<String>,        // 累计工作量最大的区块头的hash
+    height: AtomicUsize,        // 区块头链的高度
+    params: ChainParams,        // 区块链网络参数，必须与全节点一致
+    consensus: Arc<dyn Consensus>,  // 共识引擎，轻节点只用于验证区块头
+}
+
+impl<T: KVStorage> HeaderChain<T> {
+    pub fn new(storage: Arc<T>) -> Self {
+        Self::with_params(storage, ChainParams::default())
+    }
+
+    pub fn with_params(storage: Arc<T>, params: ChainParams) -> Self {
+        // 如果db中已经存储了区块头链，则加载到内存
+        let tip = storage.get_header_tip().ok().flatten().unwrap_or_default();
+        let height = storage.get_block_index(&tip).ok().flatten()
+            .map_or(0, |index| index.get_height());
+
+        Self {
+            storage,
+            tip: RwLock::new(tip),
+            height: AtomicUsize::new(height),
+            consensus: create_consensus(&params, None),
+            params,
+        }
+    }
+
+    /*
+     * 添加一个区块头：
+     * 1. 父区块头必须已经存在，创世块只能作为空链的第一个区块头，且必须与网络参数定义的创世块相同
+     * 2. 难度必须等于根据祖先区块头计算出的难度，区块头的封装必须通过共识引擎的验证
+     * 3. 时间戳必须大于祖先区块头的中位时间，且不能超前本地时间太多
+     * 4. 累计工作量超过当前tip时，切换tip，返回true
+     */
+    pub fn add_header(&self, header: BlockHeader) -> Result<bool, BlockchainError> {
+        let hash = header.hash();
+        if self.storage.get_header(&hash)?.is_some() {
+            return Ok(false);
+        }
+
+        let prev_hash = header.get_prev_hash();
+        let (parent_index, expected_bits) = if prev_hash.is_empty() {
+            if !self.get_tip().is_empty() || hash != self.params.genesis_block().get_hash() {
+                return Err(BlockchainError::GenesisMismatch(hash));
+            }
+            (BlockIndex::default(), self.params.difficulty.initial_bits)
+        } else {
+            let parent = self.storage.get_header(&prev_hash)?
+                .ok_or_else(|| BlockchainError::UnknownParent(prev_hash.clone()))?;
+            let parent_index = self.storage.get_block_index(&prev_hash)?
+                .ok_or_else(|| BlockchainError::UnknownParent(prev_hash.clone()))?;
+            let get_header = |hash: &str| self.storage.get_header(hash).ok().flatten();
+            let bits = self.consensus.next_bits(&parent, parent_index.get_height(), &get_header);
+            self.consensus.verify_seal(&header)?;
+            // 轻节点不收集其他节点的时间样本，直接使用本地时间
+            check_block_time(&header, median_time_past(&parent, get_header), Utc::now().timestamp())?;
+            (parent_index, bits)
+        };
+
+        let bits = header.get_bits();
+        if bits != expected_bits {
+            return Err(BlockchainError::InvalidDifficulty(bits));
+        }
+
+        let index = BlockIndex::new(
+            parent_index.get_height() + 1,
+            parent_index.get_chain_work() + ProofOfWork::work(bits),
+        );
+        self.storage.put_header(&hash, &header);
+        self.storage.put_block_index(&hash, &index);
+
+        let tip_work = self.storage.get_block_index(&self.get_tip())?
+            .map(|index| index.get_chain_work())
+            .unwrap_or_default();
+        if index.get_chain_work() > tip_work {
+            self.storage.set_header_tip(&hash);
+            self.height.store(index.get_height(), Ordering::Relaxed);
+            *self.tip.write().unwrap() = hash;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /*
+     * 验证一笔交易属于主链上的某个区块头，返回该区块的确认数
+     * 1. 区块头必须在本地主链上
+     * 2. Merkle证明计算出的根必须等于区块头中的txs_hash
+     */
+    pub fn verify_proof(&self, header: &BlockHeader, proof: &MerkleProof) -> Result<usize, BlockchainError> {
+        let hash = header.hash();
+        let index = self.storage.get_block_index(&hash)?
+            .ok_or_else(|| BlockchainError::UnknownBlock(hash.clone()))?;
+        if !self.is_in_main_chain(&hash, index.get_height())? {
+            return Err(BlockchainError::UnknownBlock(hash));
+        }
+
+        if !proof.verify(&header.get_txs_hash()) {
+            return Err(BlockchainError::InvalidMerkleProof(proof.get_leaf()));
+        }
+
+        Ok(self.get_height() - index.get_height() + 1)
+    }
+
+    // 从tip回溯到指定高度，判断该高度上的区块头是否为hash
+    fn is_in_main_chain(&self, hash: &str, height: usize) -> Result<bool, BlockchainError> {
+        let mut curr_hash = self.get_tip();
+        let mut curr_height = self.get_height();
+        while curr_height > height {
+            match self.storage.get_header(&curr_hash)? {
+                Some(header) => curr_hash = header.get_prev_hash(),
+                None => return Ok(false),
+            }
+            curr_height -= 1;
+        }
+        Ok(curr_hash == hash)
+    }
+
+    /*
+     * 区块定位器：从tip开始回溯的区块hash列表，前10个逐个回溯，之后步长翻倍。
+     * 全节点据此找到双方主链的分叉点，只发送分叉点之后的区块头。
+     */
+    pub fn get_locator(&self) -> Vec<String> {
+        // 从tip开始回溯整条主链
+        let mut hashes = vec![];
+        let mut curr_hash = self.get_tip();
+        while let Some(header) = self.storage.get_header(&curr_hash).ok().flatten() {
+            hashes.push(curr_hash);
+            curr_hash = header.get_prev_hash();
+        }
+
+        let mut locator = vec![];
+        let mut idx = 0;
+        let mut step = 1;
+        while idx < hashes.len() {
+            locator.push(hashes[idx].clone());
+            if locator.len() >= 10 {
+                step *= 2;
+            }
+            idx += step;
+        }
+        // 创世块总是包含在定位器中
+        if let Some(genesis) = hashes.last() {
+            if locator.last() != Some(genesis) {
+                locator.push(genesis.clone());
+            }
+        }
+        locator
+    }
+
+    pub fn get_params(&self) -> &ChainParams {
+        &self.params
+    }
+
+    pub fn get_tip(&self) -> String {
+        self.tip.read().unwrap().to_string()
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height.load(Ordering::Relaxed)
+    }
+}
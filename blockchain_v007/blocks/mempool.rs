@@ -26,6 +26,17 @@ impl MemoryPool {
         self.inner.write().unwrap().insert(txid, tx);
     }
 
+    // 查找mempool中与tx花费同一个输出的其他交易，返回第一个被重复花费的输出
+    pub fn find_conflict(&self, tx: &Transaction) -> Option<(String, usize)> {
+        let inner = self.inner.read().unwrap();
+        tx.get_vin().iter()
+            .map(|vin| (vin.get_txid(), vin.get_vout()))
+            .find(|(txid, vout)| inner.values()
+                .filter(|pooled| pooled.get_id() != tx.get_id())
+                .flat_map(|pooled| pooled.get_vin())
+                .any(|vin| vin.get_txid() == *txid && vin.get_vout() == *vout))
+    }
+
     // 根据txid获取mempool中的一笔交易
     pub fn get(&self, txid: &str) -> Option<Transaction> {
         if let Some(tx) = self.inner.read().unwrap().get(txid) {
@@ -0,0 +1,99 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{Transaction, utils::hash_to_str};
+
+/*
+ * Merkle树：叶子节点是区块中每笔交易的完整hash，两两拼接后计算hash得到上一层，
+ * 直到只剩下根节点。某一层节点数为奇数时，复制最后一个节点与其配对。
+ * 根节点存储在区块头的txs_hash中。交易id不包含解锁脚本，叶子节点使用包含解锁脚本的
+ * 完整hash，区块头才能承诺交易的签名，中继节点无法替换签名而不改变区块hash。
+ */
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    levels: Vec<Vec<String>>,   // 从叶子层到根的每一层节点
+}
+
+impl MerkleTree {
+    pub fn new(leaves: &[String]) -> Self {
+        let mut levels = vec![leaves.to_vec()];
+        while levels.last().unwrap().len() > 1 {
+            let level = levels.last().unwrap();
+            let parents = level.chunks(2)
+                .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+                .collect();
+            levels.push(parents);
+        }
+        Self { levels }
+    }
+
+    pub fn from_transactions(txs: &[Transaction]) -> Self {
+        let leaves = txs.iter().map(|tx| tx.get_full_hash()).collect::<Vec<_>>();
+        Self::new(&leaves)
+    }
+
+    // 根节点的hash，空树的根为空数据的hash
+    pub fn get_root(&self) -> String {
+        match self.levels.last().unwrap().first() {
+            Some(root) => root.clone(),
+            None => hash_to_str(&[]),
+        }
+    }
+
+    // 生成叶子节点的包含证明：从叶子到根的路径上，每一层的兄弟节点
+    pub fn get_proof(&self, leaf: &str) -> Option<MerkleProof> {
+        let leaves = self.levels.first().unwrap();
+        let index = leaves.iter().position(|hash| hash == leaf)?;
+
+        let mut siblings = vec![];
+        let mut idx = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = if idx.is_multiple_of(2) {
+                level.get(idx + 1).unwrap_or(&level[idx])
+            } else {
+                &level[idx - 1]
+            };
+            siblings.push(sibling.clone());
+            idx /= 2;
+        }
+
+        Some(MerkleProof {
+            leaf: leaf.to_string(),
+            index,
+            siblings,
+        })
+    }
+}
+
+/* Merkle包含证明，无需完整区块即可证明一笔交易属于某个区块 */
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    leaf: String,           // 被证明交易的完整hash
+    index: usize,           // 交易在区块中的位置
+    siblings: Vec<String>,  // 从叶子到根，每一层的兄弟节点
+}
+
+impl MerkleProof {
+    // 沿着证明路径重新计算根节点，并与区块头中的txs_hash比较
+    pub fn verify(&self, root: &str) -> bool {
+        let mut hash = self.leaf.clone();
+        let mut idx = self.index;
+        for sibling in &self.siblings {
+            hash = if idx.is_multiple_of(2) {
+                hash_pair(&hash, sibling)
+            } else {
+                hash_pair(sibling, &hash)
+            };
+            idx /= 2;
+        }
+        hash == root
+    }
+
+    pub fn get_leaf(&self) -> String {
+        self.leaf.clone()
+    }
+}
+
+// 将左右两个子节点拼接后计算父节点的hash
+fn hash_pair(left: &str, right: &str) -> String {
+    hash_to_str(format!("{}{}", left, right).as_bytes())
+}
@@ -0,0 +1,97 @@
+use std::{
+    sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, Ordering}},
+    thread,
+    time::Instant,
+};
+use tracing::info;
+
+use crate::{Block, ProofOfWork, utils::{serialize, hash_to_str}};
+
+/*
+ * 多线程挖矿引擎
+ *     将nonce空间按线程数交错切分：第i个线程依次尝试i, i+threads, i+2*threads...
+ * 任意一个线程找到满足难度的hash后，其余线程立即停止；外部也可以通过cancel
+ * 标志随时取消挖矿，例如收到了其他节点挖到的新块，当前tip已经过时。
+ */
+#[derive(Clone)]
+pub struct Miner {
+    threads: usize,             // 挖矿线程数
+    hash_rate: Arc<AtomicU64>,  // 最近一次挖矿的算力，单位为hash/s
+}
+
+impl Default for Miner {
+    fn default() -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::new(threads)
+    }
+}
+
+impl Miner {
+    pub fn new(threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            hash_rate: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    // 对区块挖矿，找到解则设置区块的nonce和hash并返回true；被取消则返回false
+    pub fn mine(&self, block: &mut Block, cancel: &AtomicBool) -> bool {
+        let header = block.get_header();
+        let pow = ProofOfWork::new(header.get_bits());
+        let found = AtomicBool::new(false);
+        let solution = Mutex::new(None);
+        let hashes = AtomicU64::new(0);
+        let start = Instant::now();
+
+        thread::scope(|scope| {
+            for id in 0..self.threads {
+                let mut header = header.clone();
+                let (pow, found, solution, hashes) = (&pow, &found, &solution, &hashes);
+                scope.spawn(move || {
+                    let mut nonce = id;
+                    let mut count = 0;
+                    while !found.load(Ordering::Relaxed) && !cancel.load(Ordering::Relaxed) {
+                        header.set_nonce(nonce);
+                        if let Ok(header_ser) = serialize(&header) {
+                            count += 1;
+                            // 如果hash值小于target，则满足条件；否则进行下一轮计算
+                            if pow.is_below_target(&header_ser) {
+                                found.store(true, Ordering::Relaxed);
+                                *solution.lock().unwrap() = Some((nonce, hash_to_str(&header_ser)));
+                                break;
+                            }
+                        }
+                        match nonce.checked_add(self.threads) {
+                            Some(next) => nonce = next,
+                            None => break,
+                        }
+                    }
+                    hashes.fetch_add(count, Ordering::Relaxed);
+                });
+            }
+        });
+
+        // 统计本次挖矿的算力
+        let hashes = hashes.load(Ordering::Relaxed);
+        let elapsed = start.elapsed().as_secs_f64();
+        let hash_rate = if elapsed > 0.0 { (hashes as f64 / elapsed) as u64 } else { hashes };
+        self.hash_rate.store(hash_rate, Ordering::Relaxed);
+
+        match solution.into_inner().unwrap() {
+            Some((nonce, hash)) => {
+                info!("Mined block {} with {} threads: {} hashes, {} H/s", hash, self.threads, hashes, hash_rate);
+                block.set_nonce(nonce);
+                block.set_hash(hash);
+                true
+            },
+            None => {
+                info!("Mining cancelled after {} hashes, {} H/s", hashes, hash_rate);
+                false
+            },
+        }
+    }
+
+    pub fn get_hash_rate(&self) -> u64 {
+        self.hash_rate.load(Ordering::Relaxed)
+    }
+}
@@ -1,9 +1,31 @@
 mod block;
+mod block_index;
+mod difficulty;
+mod subsidy;
+mod chain_params;
+mod versionbits;
+mod timedata;
+mod merkle;
 mod blockchain;
+mod header_chain;
 mod pow;
+mod miner;
+mod consensus;
 mod mempool;
+mod orphan_pool;
 
-pub use block::Block;
+pub use block::{Block, BlockHeader};
+pub use block_index::BlockIndex;
+pub use difficulty::DifficultyParams;
+pub use subsidy::SubsidyParams;
+pub use chain_params::*;
+pub use versionbits::*;
+pub use timedata::*;
+pub use merkle::*;
 pub use blockchain::*;
+pub use header_chain::HeaderChain;
 pub use pow::ProofOfWork;
+pub use miner::Miner;
+pub use consensus::*;
 pub use mempool::*;
+pub use orphan_pool::*;
@@ -0,0 +1,114 @@
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use crate::Block;
+
+// 孤块池中最多保存的区块数量
+pub const MAX_ORPHAN_BLOCKS: usize = 100;
+// 孤块在池中保存的最长时间
+pub const ORPHAN_EXPIRE_TIME: Duration = Duration::from_secs(600);
+
+/*
+ * 孤块池
+ *     gossipsub不保证消息顺序，子区块可能先于父区块到达。父区块未知的区块暂存在孤块池中，
+ * 以prev_hash为索引；父区块加入区块链之后，递归地取出并连接它的所有子区块。
+ */
+pub struct OrphanPool {
+    orphans: HashMap<String, (Block, Instant)>,     // 区块hash -> (孤块, 加入时间)
+    by_prev: HashMap<String, Vec<String>>,          // prev_hash -> 孤块hash列表
+    max_orphans: usize,     // 最多保存的孤块数量
+    expire_time: Duration,  // 孤块的最长保存时间
+}
+
+impl Default for OrphanPool {
+    fn default() -> Self {
+        Self::new(MAX_ORPHAN_BLOCKS, ORPHAN_EXPIRE_TIME)
+    }
+}
+
+impl OrphanPool {
+    pub fn new(max_orphans: usize, expire_time: Duration) -> Self {
+        Self {
+            orphans: HashMap::new(),
+            by_prev: HashMap::new(),
+            max_orphans,
+            expire_time,
+        }
+    }
+
+    // 根据区块hash，判断孤块池中是否包含该区块
+    pub fn contains(&self, hash: &str) -> bool {
+        self.orphans.contains_key(hash)
+    }
+
+    // 加入一个孤块，先清理过期的孤块；池已满时丢弃最早加入的孤块
+    pub fn add(&mut self, block: Block) {
+        let hash = block.get_hash();
+        if self.max_orphans == 0 || self.contains(&hash) {
+            return;
+        }
+
+        self.expire();
+        while self.orphans.len() >= self.max_orphans {
+            let oldest = self.orphans.iter()
+                .min_by_key(|(_, (_, added))| *added)
+                .map(|(hash, _)| hash.clone());
+            match oldest {
+                Some(oldest) => self.remove(&oldest),
+                None => break,
+            }
+        }
+
+        self.by_prev.entry(block.get_prev_hash()).or_default().push(hash.clone());
+        self.orphans.insert(hash, (block, Instant::now()));
+    }
+
+    // 取出并删除所有以prev_hash为父区块的孤块
+    pub fn take_children(&mut self, prev_hash: &str) -> Vec<Block> {
+        let hashes = self.by_prev.remove(prev_hash).unwrap_or_default();
+        hashes.iter()
+            .filter_map(|hash| self.orphans.remove(hash))
+            .map(|(block, _)| block)
+            .collect()
+    }
+
+    // 沿着孤块的prev_hash向前回溯，返回最早缺失的祖先区块hash，即需要向其他节点请求的区块
+    pub fn get_missing_ancestor(&self, hash: &str) -> String {
+        let mut missing = hash.to_string();
+        while let Some((block, _)) = self.orphans.get(&missing) {
+            missing = block.get_prev_hash();
+        }
+        missing
+    }
+
+    // 删除所有超过expire_time的孤块
+    pub fn expire(&mut self) {
+        let expired: Vec<String> = self.orphans.iter()
+            .filter(|(_, (_, added))| added.elapsed() > self.expire_time)
+            .map(|(hash, _)| hash.clone())
+            .collect();
+        for hash in expired {
+            self.remove(&hash);
+        }
+    }
+
+    fn remove(&mut self, hash: &str) {
+        if let Some((block, _)) = self.orphans.remove(hash) {
+            let prev_hash = block.get_prev_hash();
+            if let Some(children) = self.by_prev.get_mut(&prev_hash) {
+                children.retain(|child| child != hash);
+                if children.is_empty() {
+                    self.by_prev.remove(&prev_hash);
+                }
+            }
+        }
+    }
+
+    // 返回孤块池当前存储的区块数量
+    pub fn len(&self) -> usize {
+        self.orphans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orphans.is_empty()
+    }
+}
@@ -1,55 +1,93 @@
-use anyhow::Result;
-use std::ops::Shl;
 use bigint::U256;
 
-use crate::{utils::{serialize, hash_to_u8, hash_to_str}, Block};
+use crate::{utils::{serialize, hash_to_u8, hash_to_str}, Block, BlockHeader, error::BlockchainError};
 
-// 挖矿最大迭代次数
-const MAX_NONCE: usize = usize::MAX;
-
-/* 工作量证明机制 */
+/*
+ * 工作量证明机制
+ *     区块头中的bits是target的紧凑编码（与比特币的nBits相同）：
+ * 最高字节是target的字节数size，低3个字节是target的最高有效位mantissa，
+ * target = mantissa * 256^(size - 3)。mantissa的最高位是符号位，必须为0。
+ */
 pub struct ProofOfWork {
     target: U256,   // 根据bits计算的工作难度，bigint库的U256类型
 }
 
 impl ProofOfWork {
-    // 构造函数
-    pub fn new(bits: usize) -> Self {
-        // target初始化为1
-        let mut target = U256::from(1 as usize);
-        // left shift
-        target = target.shl(256 - bits);
-
+    // 构造函数，bits不是合法的紧凑编码时target为0，任何hash都无法满足
+    pub fn new(bits: u32) -> Self {
         Self {
-            target
+            target: Self::compact_to_target(bits).unwrap_or_default(),
         }
     }
 
-    // 根据传入的nonce，对区块头序列化
-    fn prepare_data(block: &mut Block, nonce: usize) -> Result<Vec<u8>> {
-        block.set_nonce(nonce);
-        Ok(serialize(&(block.get_header()))?)
+    // 将紧凑编码转换为target，编码为负数或者超出256位时返回None
+    pub fn compact_to_target(bits: u32) -> Option<U256> {
+        let size = (bits >> 24) as usize;
+        let mantissa = bits & 0x007f_ffff;
+        if mantissa != 0 && bits & 0x0080_0000 != 0 {
+            return None;
+        }
+        if mantissa != 0 && (size > 34 || (mantissa > 0xff && size > 33) || (mantissa > 0xffff && size > 32)) {
+            return None;
+        }
+
+        let target = if size <= 3 {
+            U256::from(mantissa >> (8 * (3 - size)))
+        } else {
+            U256::from(mantissa) << (8 * (size - 3))
+        };
+        Some(target)
     }
 
-    // 挖矿过程：遍历nonce寻找满足工作难度的hash
-    pub fn run(&self, block: &mut Block) {
-        let mut nonce = 0;
-
-        while nonce < MAX_NONCE {
-            if let Ok(iter_hash) = Self::prepare_data(block, nonce) {
-                let mut hash_u: [u8; 32] = [0; 32];
-                hash_to_u8(&iter_hash, &mut hash_u);
-                let iter_hash_int = U256::from(hash_u);
-
-                // 如果hash值小于target，则满足条件；否则进行下一轮计算
-                if iter_hash_int.lt(&(self.target)) {
-                    block.set_hash(hash_to_str(&iter_hash));
-                    break;
-                } else {
-                    nonce += 1;
-                }
-            }
+    // 将target转换为紧凑编码，只保留最高的3个有效字节
+    pub fn target_to_compact(target: U256) -> u32 {
+        let mut size = target.bits().div_ceil(8);
+        let mut mantissa = if size <= 3 {
+            target.low_u32() << (8 * (3 - size))
+        } else {
+            (target >> (8 * (size - 3))).low_u32()
+        };
+        // mantissa的最高位是符号位，为1时右移一个字节
+        if mantissa & 0x0080_0000 != 0 {
+            mantissa >>= 8;
+            size += 1;
+        }
+        mantissa | (size as u32) << 24
+    }
+
+    // 一个区块的工作量，即找到满足难度的hash平均需要的计算次数：2^256 / (target + 1)
+    pub fn work(bits: u32) -> U256 {
+        match Self::compact_to_target(bits) {
+            Some(target) if !target.is_zero() => {
+                // 2^256超出了U256的范围，利用 2^256 / (target + 1) = (2^256 - target - 1) / (target + 1) + 1 计算
+                (!target / (target + U256::one())) + U256::one()
+            },
+            _ => U256::zero(),
         }
     }
-}
 
+    // 判断序列化后的区块头的hash是否小于target
+    pub fn is_below_target(&self, header_ser: &[u8]) -> bool {
+        let mut hash_u: [u8; 32] = [0; 32];
+        hash_to_u8(header_ser, &mut hash_u);
+        U256::from(hash_u).lt(&(self.target))
+    }
+
+    // 验证区块的工作量证明：区块hash必须由区块头计算得出，并且小于target
+    pub fn validate(&self, block: &Block) -> Result<(), BlockchainError> {
+        if block.get_header().hash() != block.get_hash() {
+            return Err(BlockchainError::InvalidBlockHash(block.get_hash()));
+        }
+        self.validate_header(&block.get_header())
+    }
+
+    // 验证区块头的hash是否小于target，轻节点只需要验证区块头
+    pub fn validate_header(&self, header: &BlockHeader) -> Result<(), BlockchainError> {
+        let header_ser = serialize(header)?;
+        if self.is_below_target(&header_ser) {
+            Ok(())
+        } else {
+            Err(BlockchainError::InvalidProofOfWork(hash_to_str(&header_ser)))
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::Amount;
+
+/*
+ * 挖矿奖励参数
+ *     每个区块的奖励随高度减半：高度1到halving_interval的区块奖励为initial_subsidy，
+ * 之后每经过halving_interval个区块减半一次，直到为0。
+ * 所有区块奖励的总和不超过max_supply。
+ */
+#[derive(Debug, Clone)]
+pub struct SubsidyParams {
+    pub initial_subsidy: Amount,    // 创世块开始的区块奖励
+    pub halving_interval: usize,    // 每隔多少个区块奖励减半
+    pub max_supply: Amount,         // 代币的最大发行总量
+}
+
+impl Default for SubsidyParams {
+    fn default() -> Self {
+        Self {
+            initial_subsidy: Amount::from_coins(20),
+            halving_interval: 1000,
+            max_supply: Amount::from_coins(40_000),
+        }
+    }
+}
+
+impl SubsidyParams {
+    // 不考虑发行总量上限时，高度为height的区块奖励，height从1开始
+    fn scheduled_subsidy(&self, height: usize) -> Amount {
+        if height == 0 {
+            return Amount::ZERO;
+        }
+        if self.halving_interval == 0 {
+            return self.initial_subsidy;
+        }
+        let halvings = (height - 1) / self.halving_interval;
+        u32::try_from(halvings).ok()
+            .and_then(|halvings| self.initial_subsidy.as_sat().checked_shr(halvings))
+            .map_or(Amount::ZERO, Amount::from_sat)
+    }
+
+    // 高度为height的区块允许铸造的奖励，发行总量达到上限后为0
+    pub fn subsidy(&self, height: usize) -> Amount {
+        let remaining = self.max_supply.checked_sub(self.circulating_supply(height.saturating_sub(1))).unwrap_or_default();
+        self.scheduled_subsidy(height).min(remaining)
+    }
+
+    // 高度1到height的所有区块奖励之和，即height高度时的流通量
+    pub fn circulating_supply(&self, height: usize) -> Amount {
+        let mut supply = Amount::ZERO;
+        let mut start = 1;
+        while start <= height {
+            let subsidy = self.scheduled_subsidy(start);
+            if subsidy == Amount::ZERO {
+                break;
+            }
+            // 同一个减半周期内的区块奖励相同
+            let end = match (start - 1).checked_div(self.halving_interval) {
+                Some(halvings) => height.min((halvings + 1) * self.halving_interval),
+                None => height,
+            };
+            supply = match subsidy.checked_mul((end - start + 1) as u64).and_then(|amount| supply.checked_add(amount)) {
+                Some(supply) if supply < self.max_supply => supply,
+                _ => return self.max_supply,
+            };
+            start = end + 1;
+        }
+        supply
+    }
+}
@@ -0,0 +1,96 @@
+use std::{collections::HashMap, sync::RwLock};
+use chrono::Utc;
+
+use crate::{BlockHeader, error::BlockchainError};
+
+// 计算中位时间（median time past）所用的祖先区块数量
+pub const MEDIAN_TIME_SPAN: usize = 11;
+// 区块时间戳最多可以超前网络调整时间的秒数
+pub const MAX_FUTURE_BLOCK_TIME: i64 = 2 * 60 * 60;
+// 网络时间偏移量的上限，超过时认为本地时钟或其他节点的时钟有误，不做调整
+pub const MAX_TIME_ADJUSTMENT: i64 = 70 * 60;
+// 至少收集到多少个节点的时间样本，才开始调整本地时间
+const MIN_TIME_SAMPLES: usize = 5;
+// 最多保存的时间样本数量
+const MAX_TIME_SAMPLES: usize = 200;
+
+/*
+ * 网络调整时间
+ *     每个节点在Version消息中带上自己的本地时间，收到后记录对方与本地时间的偏移量。
+ * 网络调整时间 = 本地时间 + 所有偏移量的中位数，用于判断区块时间戳是否超前太多。
+ */
+#[derive(Default)]
+pub struct TimeData {
+    offsets: RwLock<HashMap<String, i64>>,  // peer id -> 对方时间与本地时间的偏移量，单位为秒
+}
+
+impl TimeData {
+    // 记录一个节点的时间样本，每个节点只保留最新的样本
+    pub fn add_sample(&self, peer: &str, peer_time: i64) {
+        let mut offsets = self.offsets.write().unwrap();
+        if offsets.len() >= MAX_TIME_SAMPLES && !offsets.contains_key(peer) {
+            return;
+        }
+        offsets.insert(peer.to_string(), peer_time - Utc::now().timestamp());
+    }
+
+    // 所有偏移量的中位数；样本不足，或者偏移量超过MAX_TIME_ADJUSTMENT时返回0
+    pub fn get_offset(&self) -> i64 {
+        let offsets = self.offsets.read().unwrap();
+        if offsets.len() < MIN_TIME_SAMPLES {
+            return 0;
+        }
+
+        let mut values: Vec<i64> = offsets.values().copied().collect();
+        values.sort_unstable();
+        let median = values[values.len() / 2];
+        if median.abs() > MAX_TIME_ADJUSTMENT {
+            return 0;
+        }
+        median
+    }
+
+    // 网络调整时间，单位为秒
+    pub fn adjusted_time(&self) -> i64 {
+        Utc::now().timestamp() + self.get_offset()
+    }
+}
+
+/*
+ * 计算parent之后下一个区块的中位时间：parent及其之前共MEDIAN_TIME_SPAN个区块时间戳的中位数
+ * get_header: 根据区块hash查找区块头，用于回溯祖先区块的时间戳
+ */
+pub fn median_time_past<F>(parent: &BlockHeader, get_header: F) -> i64
+where
+    F: Fn(&str) -> Option<BlockHeader>
+{
+    let mut timestamps = vec![parent.get_timestamp()];
+    let mut prev_hash = parent.get_prev_hash();
+    while timestamps.len() < MEDIAN_TIME_SPAN && !prev_hash.is_empty() {
+        match get_header(&prev_hash) {
+            Some(header) => {
+                timestamps.push(header.get_timestamp());
+                prev_hash = header.get_prev_hash();
+            },
+            None => break,
+        }
+    }
+    timestamps.sort_unstable();
+    timestamps[timestamps.len() / 2]
+}
+
+/*
+ * 区块时间戳规则：
+ * 1. 必须大于父区块之前的中位时间，保证时间随区块单调推进
+ * 2. 不能超过网络调整时间MAX_FUTURE_BLOCK_TIME秒以上
+ */
+pub fn check_block_time(header: &BlockHeader, median_time: i64, adjusted_time: i64) -> Result<(), BlockchainError> {
+    let timestamp = header.get_timestamp();
+    if timestamp <= median_time {
+        return Err(BlockchainError::TimeTooOld(timestamp, median_time));
+    }
+    if timestamp > adjusted_time + MAX_FUTURE_BLOCK_TIME {
+        return Err(BlockchainError::TimeTooNew(timestamp));
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use serde::{Serialize, Deserialize};
+
+// 使用版本位信号的区块版本号，最高3位固定为001，其余29位用于表示对各个软分叉的支持
+pub const VERSIONBITS_TOP_BITS: u32 = 0x2000_0000;
+pub const VERSIONBITS_TOP_MASK: u32 = 0xe000_0000;
+
+// 相对时间锁的部署名称，网络未定义该部署时，相对时间锁从创世块开始生效
+pub const DEPLOYMENT_CSV: &str = "csv";
+
+/* 软分叉部署的状态 */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeploymentState {
+    Defined,    // 尚未到达开始高度
+    Started,    // 矿工可以在区块版本中发出信号
+    LockedIn,   // 某个窗口内发出信号的区块数达到阈值，下一个窗口激活
+    Active,     // 新规则生效
+    Failed,     // 超时仍未锁定，部署失败
+}
+
+/*
+ * 一个软分叉部署
+ *     从start_height开始，矿工通过设置区块版本的第bit位表示支持；
+ * 在一个窗口内发出信号的区块数达到阈值则锁定，并在下一个窗口开始时激活。
+ */
+#[derive(Debug, Clone)]
+pub struct Deployment {
+    pub name: String,           // 部署的名称
+    pub bit: u8,                // 区块版本中用于发出信号的位，取值0到28
+    pub start_height: usize,    // 从该高度所在的窗口开始统计信号
+    pub timeout_height: usize,  // 到达该高度仍未锁定，则部署失败
+}
+
+impl Deployment {
+    // 区块版本是否对该部署发出了信号
+    pub fn is_signalled(&self, version: u32) -> bool {
+        version & VERSIONBITS_TOP_MASK == VERSIONBITS_TOP_BITS && version & self.mask() != 0
+    }
+
+    pub fn mask(&self) -> u32 {
+        1 << self.bit
+    }
+}
+
+/* 版本位信号参数 */
+#[derive(Debug, Clone)]
+pub struct VersionBitsParams {
+    pub window: usize,              // 统计信号的窗口大小，单位为区块数
+    pub threshold: usize,           // 一个窗口内锁定部署所需的信号区块数
+    pub deployments: Vec<Deployment>,   // 所有软分叉部署
+}
+
+impl Default for VersionBitsParams {
+    fn default() -> Self {
+        Self {
+            window: 20,
+            threshold: 15,
+            deployments: vec![],
+        }
+    }
+}
+
+impl VersionBitsParams {
+    pub fn get_deployment(&self, name: &str) -> Option<&Deployment> {
+        self.deployments.iter().find(|deployment| deployment.name == name)
+    }
+
+    /*
+     * 计算高度为height的区块所处的部署状态，状态只在窗口边界上变化
+     * versions: 该区块所有祖先区块的版本，versions[i]是高度为i+1的区块的版本
+     */
+    pub fn get_state(&self, deployment: &Deployment, height: usize, versions: &[u32]) -> DeploymentState {
+        if self.window == 0 || height == 0 {
+            return DeploymentState::Defined;
+        }
+
+        let mut state = DeploymentState::Defined;
+        let last_period = (height - 1) / self.window;
+        for period in 0..last_period {
+            let signals = versions.iter()
+                .skip(period * self.window)
+                .take(self.window)
+                .filter(|version| deployment.is_signalled(**version))
+                .count();
+            // 下一个窗口的第一个区块的高度
+            state = self.next_state(deployment, state, signals, (period + 1) * self.window + 1);
+        }
+        state
+    }
+
+    /*
+     * 根据一个窗口的状态和窗口内发出信号的区块数，计算下一个窗口的状态
+     * next_start: 下一个窗口的第一个区块的高度
+     */
+    pub fn next_state(&self, deployment: &Deployment, state: DeploymentState, signals: usize, next_start: usize) -> DeploymentState {
+        match state {
+            DeploymentState::Defined if next_start >= deployment.timeout_height => DeploymentState::Failed,
+            DeploymentState::Defined if next_start >= deployment.start_height => DeploymentState::Started,
+            DeploymentState::Started => {
+                if signals >= self.threshold {
+                    DeploymentState::LockedIn
+                } else if next_start >= deployment.timeout_height {
+                    DeploymentState::Failed
+                } else {
+                    DeploymentState::Started
+                }
+            },
+            DeploymentState::LockedIn => DeploymentState::Active,
+            state => state,
+        }
+    }
+
+    // 新区块应当使用的版本：对所有处于Started和LockedIn状态的部署发出信号，states[i]是deployments[i]在该区块上的状态
+    pub fn compute_version(&self, states: &[DeploymentState]) -> u32 {
+        self.deployments.iter()
+            .zip(states)
+            .filter(|(_, state)| matches!(state, DeploymentState::Started | DeploymentState::LockedIn))
+            .fold(VERSIONBITS_TOP_BITS, |version, (deployment, _)| version | deployment.mask())
+    }
+}
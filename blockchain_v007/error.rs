@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::Amount;
+
 /* 错误信息 */
 #[derive(Debug, Error)]
 pub enum BlockchainError {
@@ -8,5 +10,115 @@ pub enum BlockchainError {
 
     #[error("Failed to access rocks db")]
     RocksDbError(#[from] rocksdb::Error),
-}
 
+    #[error("Block prev_hash {0} does not match current tip {1}")]
+    PrevHashMismatch(String, String),
+
+    #[error("Parent block {0} is unknown")]
+    UnknownParent(String),
+
+    #[error("Genesis block {0} does not match the local chain")]
+    GenesisMismatch(String),
+
+    #[error("Block bits {0:#010x} does not match the required difficulty")]
+    InvalidDifficulty(u32),
+
+    #[error("Block hash {0} does not match its header")]
+    InvalidBlockHash(String),
+
+    #[error("Block hash {0} does not meet the proof of work target")]
+    InvalidProofOfWork(String),
+
+    #[error("Block txs_hash does not match its transactions")]
+    InvalidTxsHash,
+
+    #[error("Block must have exactly one coinbase transaction at the first position")]
+    InvalidCoinbase,
+
+    #[error("Transaction {0} failed verification")]
+    InvalidTransaction(String),
+
+    #[error("Block {0} is not in the main chain")]
+    UnknownBlock(String),
+
+    #[error("Merkle proof of transaction {0} is invalid")]
+    InvalidMerkleProof(String),
+
+    #[error("Coinbase pays {0}, more than the allowed {1}")]
+    CoinbaseOverpays(Amount, Amount),
+
+    #[error("Coinbase does not commit to the block height {0}")]
+    InvalidCoinbaseHeight(usize),
+
+    #[error("Block version {0:#010x} is not supported")]
+    InvalidBlockVersion(u32),
+
+    #[error("Block {1} at height {0} does not match the checkpoint")]
+    CheckpointMismatch(usize, String),
+
+    #[error("Block at height {0} forks the chain below a checkpoint")]
+    ForkBelowCheckpoint(usize),
+
+    #[error("Block time {0} is not after the median time past {1}")]
+    TimeTooOld(i64, i64),
+
+    #[error("Block time {0} is too far in the future")]
+    TimeTooNew(i64),
+
+    #[error("Block size {0} exceeds the limit {1}")]
+    BlockTooLarge(usize, usize),
+
+    #[error("Block has {0} signature operations, more than the limit {1}")]
+    TooManySigops(usize, usize),
+
+    #[error("Block {0} is not sealed by the consensus engine")]
+    InvalidSeal(String),
+
+    #[error("Not enough funds: need {0}, have {1}")]
+    InsufficientFunds(Amount, Amount),
+
+    #[error("Wallet {0} does not exist")]
+    UnknownWallet(String),
+
+    #[error("Previous transaction output of {0} does not exist")]
+    MissingPrevTx(String),
+
+    #[error("Signature of transaction {0} is invalid")]
+    InvalidSignature(String),
+
+    #[error("Address {0} is invalid on this network")]
+    InvalidAddress(String),
+
+    #[error("Script evaluation failed: {0}")]
+    ScriptFailed(&'static str),
+
+    #[error("Invalid multisig: {0} of {1} public keys")]
+    InvalidMultisig(usize, usize),
+
+    #[error("Transaction {0} is not final, its lock time has not been reached")]
+    NonFinalTransaction(String),
+
+    #[error("Transaction {0} spends an output whose relative lock has not expired")]
+    SequenceLocked(String),
+
+    #[error("Output {0}:{1} is not a hash time-locked contract, or the preimage does not match")]
+    InvalidHtlc(String, usize),
+
+    #[error("Amount {0} is invalid, it must be a non-negative number with at most 8 decimals")]
+    InvalidAmount(String),
+
+    #[error("Output {0}:{1} is spent more than once")]
+    DoubleSpend(String, usize),
+
+    #[error("Invalid mining policy: {0}")]
+    InvalidMiningPolicy(&'static str),
+
+    #[error("Block {0} is invalid or descends from an invalid block")]
+    InvalidChain(String),
+
+    #[error("Transaction {0} appears more than once in the block")]
+    DuplicateTransaction(String),
+
+    #[error("Undo data of block {0} is missing")]
+    MissingBlockUndo(String),
+}
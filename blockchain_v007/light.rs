@@ -0,0 +1,37 @@
+use std::{env::{current_dir, self}, sync::Arc};
+use anyhow::Result;
+
+use hungrytiger::{RocksDb, LightNode, ChainParams};
+
+/* 以轻节点方式启动，只同步区块头，并验证支付给本地地址的交易 */
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    // 第二个参数是区块头存储路径，默认为light_data目录
+    let mut path = String::from("light_data");
+    if let Some(args) = env::args().nth(1) {
+        path = args;
+    }
+
+    // 第三个参数是网络名称：main、test、regtest、dev或private，默认为main
+    // private网络之后的参数是验证者地址列表
+    let params = match env::args().nth(2) {
+        Some(name) if name == "private" => {
+            let validators: Vec<String> = env::args().skip(3).collect();
+            if validators.is_empty() {
+                anyhow::bail!("Private network requires at least one validator address");
+            }
+            ChainParams::private(validators)
+        },
+        Some(name) => ChainParams::from_name(&name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown network: {}", name))?,
+        None => ChainParams::main(),
+    };
+
+    let path = current_dir().unwrap().join(path);
+    let db = Arc::new(RocksDb::new(path));
+    let mut node = LightNode::with_params(db, params).await?;
+    node.start().await?;
+    Ok(())
+}
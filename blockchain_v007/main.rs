@@ -1,6 +1,6 @@
 use std::{env::current_dir, sync::Arc};
 
-use hungrytiger::{Blockchain, RocksDb, UTXOSet, Transaction, Wallets};
+use hungrytiger::{Amount, Blockchain, ChainParams, RocksDb, UTXOSet, Transaction, Wallets};
 
 /* 创建区块链 */
 fn generate_blockchain() -> String {
@@ -10,14 +10,13 @@ fn generate_blockchain() -> String {
     let path = current_dir().unwrap().join("data");
     let storage = Arc::new(RocksDb::new(path));
 
-    // 创世块的矿工地址
-    //let mut wallets = Wallets::new().unwrap();
-    //let genesis_addr = wallets.create_wallet();
-    let genesis_addr: String = String::from("1GMXdoTqE4wfG1kdxDDkYz4qNr7x3dMG8b");
+    // 创世块的矿工地址由网络参数定义
+    let params = ChainParams::main();
+    let genesis_addr = params.genesis.address.clone();
     println!("==> genesis address: {}", genesis_addr);
 
-    let mut bc = Blockchain::new(storage.clone());
-    bc.create_genesis_block(&genesis_addr);
+    let mut bc = Blockchain::with_params(storage.clone(), params);
+    bc.create_genesis_block().unwrap();
 
     let utxos = UTXOSet::new(storage);
     utxos.reindex(&bc).unwrap(); 
@@ -42,14 +41,18 @@ fn create_transactions(caodl_addr: String) {
     let mut bc = Blockchain::new(storage.clone());
     let utxos = UTXOSet::new(storage);
 
+    // coinbase交易成熟之后才能花费，先挖出足够的区块，挖矿奖励发给caodl_addr
+    for _ in 0..bc.get_params().coinbase_maturity {
+        bc.mining(caodl_addr.as_str(), &[]).unwrap();
+    }
+
+    // 两笔交易分别打包在两个区块中，第二笔交易根据第一笔交易打包之后的UTXO集合选择输入，不会重复花费同一个输出
     let tx1 = Transaction::new_utxo_transaction(
-        caodl_addr.as_str(), bob_addr.as_str(), 3, &utxos, &bc);
+        caodl_addr.as_str(), bob_addr.as_str(), Amount::from_coins(3), Amount::ZERO, &utxos, &bc).unwrap();
+    bc.mining(caodl_addr.as_str(), &[tx1]).unwrap();
     let tx2 = Transaction::new_utxo_transaction(
-        caodl_addr.as_str(), alice_addr.as_str(), 2, &utxos, &bc);
-
-    let txs = vec![tx1, tx2];
-    bc.mining(&txs);
-    utxos.reindex(&bc).unwrap();
+        caodl_addr.as_str(), alice_addr.as_str(), Amount::from_coins(2), Amount::ZERO, &utxos, &bc).unwrap();
+    bc.mining(caodl_addr.as_str(), &[tx2]).unwrap();
 
     bc.blocks_info();
 
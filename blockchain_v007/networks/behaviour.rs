@@ -1,5 +1,5 @@
 use libp2p::{
-    NetworkBehaviour, 
+    NetworkBehaviour, PeerId,
     gossipsub::{Gossipsub, GossipsubConfig, MessageAuthenticity, GossipsubEvent}, 
     mdns::{Mdns, MdnsEvent}, 
     identity::Keypair, 
@@ -18,11 +18,11 @@ pub struct BlockchainBehaviour {
     pub gossipsub: Gossipsub,       // 基于gossip的消息通信
     pub mdns: Mdns,                 // 节点发现机制
     #[behaviour(ignore)]
-    pub msg_sender: mpsc::UnboundedSender<Messages>,        // 消息发送端
+    pub msg_sender: mpsc::UnboundedSender<(PeerId, Messages)>,  // 消息发送端，同时发送转发该消息的节点
 }
 
 impl BlockchainBehaviour {
-    pub async fn new(key_pair: Keypair, config: GossipsubConfig, msg_sender: mpsc::UnboundedSender<Messages>) -> Result<Self> {
+    pub async fn new(key_pair: Keypair, config: GossipsubConfig, msg_sender: mpsc::UnboundedSender<(PeerId, Messages)>) -> Result<Self> {
         Ok(Self {
             gossipsub: Gossipsub::new(MessageAuthenticity::Signed(key_pair), config).unwrap(),
             mdns: Mdns::new(Default::default()).await?,
@@ -41,7 +41,7 @@ impl NetworkBehaviourEventProcess<GossipsubEvent> for BlockchainBehaviour {
             } => {
                 println!("Got message with id: {} from peer: {:?}", id, peer_id);
                 let msg: Messages = serde_json::from_slice(&message.data).unwrap();
-                if let Err(e) = self.msg_sender.send(msg) {
+                if let Err(e) = self.msg_sender.send((peer_id, msg)) {
                     error!("error sending messages via channel, {}", e);
                 }
             },
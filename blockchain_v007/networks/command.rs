@@ -1,6 +1,9 @@
 use serde::{Serialize, Deserialize};
 
-use crate::Block;
+use crate::{Block, BlockHeader, MerkleProof, Transaction};
+
+// 一条Headers消息中最多包含的区块头数量
+pub const MAX_HEADERS_PER_MSG: usize = 2000;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Commands {
@@ -11,17 +14,54 @@ pub enum Commands {
     GetAddress(String),
     GetBalance(String),
     ListAddresses,
+    PublicKey(String),
+    CreateMultisig {
+        required: usize,
+        public_keys: Vec<String>,   // 十六进制编码的参与者公钥
+    },
+    Sign(Transaction),
+    Send(Transaction),
+    Proof(String),
+    Supply,
+    Deployments,
+    Mine,
     Trans {
         from: String,
         to: String,
         amount: String,
+        fee: Option<String>,
+        lock_time: Option<u32>,     // 交易最早可以被打包的区块高度或时间戳
+        sequence: Option<u32>,      // 输入的序列号，用于设置相对时间锁
+    },
+    HtlcCreate {
+        from: String,               // 付款并在超时后退款的地址
+        receiver: String,           // 凭原像领取的地址
+        amount: String,
+        fee: Option<String>,
+        timeout: u32,               // 退款的区块高度或时间戳
+        hash: Option<String>,       // 十六进制编码的原像SHA256哈希，为空时生成新的原像
+    },
+    HtlcClaim {
+        txid: String,
+        vout: usize,
+        preimage: String,           // 十六进制编码的原像
+        to: String,
+        fee: Option<String>,
     },
+    HtlcRefund {
+        txid: String,
+        vout: usize,
+        to: String,
+        fee: Option<String>,
+    },
+    HtlcPreimage(String),           // 在链上查找十六进制编码的哈希对应的已公开原像
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Messages {
     Version {
         best_height: usize,
+        timestamp: i64,     // 发送节点的本地时间，用于计算网络调整时间
         from_addr: String,
     },
     Blocks {
@@ -31,5 +71,40 @@ pub enum Messages {
     },
     Block {
         block: Block,
-    }
+    },
+    Transaction {
+        transaction: Transaction,
+    },
+    GetBlock {
+        hash: String,
+    },
+    GetHeaders {
+        locator: Vec<String>,
+        from_addr: String,
+    },
+    Headers {
+        headers: Vec<BlockHeader>,
+        to_addr: String,
+    },
+    GetPayments {
+        address: String,
+        from_addr: String,
+    },
+    Payments {
+        address: String,
+        payments: Vec<(Transaction, BlockHeader, MerkleProof)>,
+        to_addr: String,
+    },
+}
+
+/* 轻节点的命令 */
+#[derive(Debug, Serialize, Deserialize)]
+pub enum LightCommands {
+    Sync,
+    Headers,
+    Payments(String),
+    Verify {
+        header: BlockHeader,
+        proof: MerkleProof,
+    },
 }
@@ -0,0 +1,202 @@
+use std::sync::Arc;
+use futures::StreamExt;
+use libp2p::{Swarm, swarm::SwarmEvent, PeerId, gossipsub::IdentTopic as Topic};
+use anyhow::Result;
+use tokio::{
+    io::{BufReader, stdin, AsyncBufReadExt},
+    sync::mpsc
+};
+use tracing::{error};
+use crate::{Amount, HeaderChain, ChainParams, BlockchainBehaviour, KVStorage, RocksDb, LightCommands, Messages, BlockHeader, MerkleProof, Transaction, MAX_HEADERS_PER_MSG, address_to_pub_key_hash};
+
+use super::{create_swarm, PEER_ID};
+
+/*
+ * 轻节点（SPV）：只同步并验证区块头，不存储区块和UTXO集合。
+ * 通过全节点提供的Merkle证明，验证支付给本地地址的交易已经被打包进主链。
+ */
+pub struct LightNode<T = RocksDb> {
+    headers: HeaderChain<T>,    // 本地的区块头链
+    msg_receiver: mpsc::UnboundedReceiver<(PeerId, Messages)>,  // 消息接收端，同时接收转发该消息的节点
+    swarm: Swarm<BlockchainBehaviour>,      // rust-libp2p的swarm
+    block_topic: Topic,     // 当前网络广播区块的topic
+}
+
+impl<T: KVStorage> LightNode<T> {
+    // 异步构造函数
+    pub async fn new(storage: Arc<T>) -> Result<Self> {
+        Self::with_params(storage, ChainParams::default()).await
+    }
+
+    // 异步构造函数，指定网络参数
+    pub async fn with_params(storage: Arc<T>, params: ChainParams) -> Result<Self> {
+        params.validate()?;
+
+        let (msg_sender, msg_receiver) = mpsc::unbounded_channel();
+        let block_topic = Topic::new(params.block_topic.as_str());
+        let tranx_topic = Topic::new(params.tranx_topic.as_str());
+
+        Ok(Self {
+            headers: HeaderChain::with_params(storage, params),
+            msg_receiver,
+            swarm: create_swarm(vec![block_topic.clone(), tranx_topic], msg_sender).await?,
+            block_topic,
+        })
+    }
+
+    // 向全节点请求本地区块头链之后的区块头
+    async fn sync(&mut self) -> Result<()> {
+        let get_headers = Messages::GetHeaders {
+            locator: self.headers.get_locator(),
+            from_addr: PEER_ID.to_string(),
+        };
+        let msg = serde_json::to_vec(&get_headers)?;
+        if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(self.block_topic.clone(), msg) {
+            error!("Request headers error: {:?}", e);
+        }
+        Ok(())
+    }
+
+    // 向全节点查询支付给address的交易
+    async fn get_payments(&mut self, address: String) -> Result<()> {
+        let get_payments = Messages::GetPayments {
+            address,
+            from_addr: PEER_ID.to_string(),
+        };
+        let msg = serde_json::to_vec(&get_payments)?;
+        if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(self.block_topic.clone(), msg) {
+            error!("Request payments error: {:?}", e);
+        }
+        Ok(())
+    }
+
+    // 处理全节点发送的区块头，若区块头数量达到上限，则继续同步
+    async fn process_headers_msg(&mut self, headers: Vec<BlockHeader>, to_addr: String) -> Result<()> {
+        if PEER_ID.to_string() != to_addr {
+            return Ok(());
+        }
+
+        let count = headers.len();
+        for header in headers {
+            if let Err(e) = self.headers.add_header(header) {
+                error!("Reject headers from peer: {}", e);
+                return Ok(());
+            }
+        }
+        println!("Header chain height: {}", self.headers.get_height());
+
+        if count >= MAX_HEADERS_PER_MSG {
+            self.sync().await?;
+        }
+        Ok(())
+    }
+
+    // 处理全节点新挖到的区块，只保留区块头；缺少父区块头时重新同步
+    async fn process_block_header(&mut self, header: BlockHeader) -> Result<()> {
+        if let Err(e) = self.headers.add_header(header) {
+            error!("Reject block header from peer: {}", e);
+            self.sync().await?;
+        }
+        Ok(())
+    }
+
+    // 逐笔验证全节点返回的支付交易
+    fn process_payments_msg(&self, address: String, payments: Vec<(Transaction, BlockHeader, MerkleProof)>, to_addr: String) {
+        if PEER_ID.to_string() != to_addr || !self.headers.get_params().is_valid_address(&address) {
+            return;
+        }
+
+        let pub_key_hash = address_to_pub_key_hash(&address);
+
+        for (tx, header, proof) in payments {
+            // 交易内容必须与证明中的叶子节点一致，否则全节点可能篡改了交易输出或签名
+            if !tx.verify_hash() || tx.get_full_hash() != proof.get_leaf() {
+                error!("Transaction {} does not match its proof", tx.get_id());
+                continue;
+            }
+            match self.headers.verify_proof(&header, &proof) {
+                Ok(confirmations) => {
+                    let value = tx.get_vout().iter()
+                        .filter(|out| out.is_locked(&pub_key_hash))
+                        .fold(Amount::ZERO, |value, out| value.saturating_add(out.get_value()));
+                    println!("Payment {} of {} to {} confirmed by {} blocks", tx.get_id(), value, address, confirmations);
+                },
+                Err(e) => error!("Invalid payment {}: {}", tx.get_id(), e),
+            }
+        }
+    }
+
+    // 启动轻节点服务
+    pub async fn start(&mut self) -> Result<()> {
+        // 监听端口
+        self.swarm.listen_on("/ip4/127.0.0.1/tcp/0".parse()?)?;
+
+        // 监听命令行输入
+        let mut stdin = BufReader::new(stdin()).lines();
+
+        loop {
+            tokio::select! {
+                // line是命令行输入
+                line = stdin.next_line() => {
+                    let line = line?.expect("stdin closed");
+                    let command = serde_json::from_str(line.as_str());
+                    match command {
+                        Ok(cmd) => match cmd {
+                            // 同步区块头
+                            LightCommands::Sync => {
+                                self.sync().await?;
+                            },
+                            // 输出区块头链状态
+                            LightCommands::Headers => {
+                                println!("tip: {}", self.headers.get_tip());
+                                println!("height: {}", self.headers.get_height());
+                            },
+                            // 查询支付给本地地址的交易
+                            LightCommands::Payments(address) => {
+                                if self.headers.get_params().is_valid_address(&address) {
+                                    self.get_payments(address).await?;
+                                } else {
+                                    println!("Invalid address: {}", address);
+                                }
+                            },
+                            // 验证第三方提供的Merkle证明
+                            LightCommands::Verify{header, proof} => {
+                                match self.headers.verify_proof(&header, &proof) {
+                                    Ok(confirmations) => println!("Transaction with hash {} confirmed by {} blocks", proof.get_leaf(), confirmations),
+                                    Err(e) => println!("Invalid proof: {}", e),
+                                }
+                            },
+                        },
+                        Err(e) => {
+                            error!("Parse command error: {}", e);
+                            continue;
+                        },
+                    }
+                },
+                // messages是通过P2P网络收到的消息
+                messages = self.msg_receiver.recv() => {
+                    if let Some((_, msg)) = messages {
+                        match msg {
+                            Messages::Headers{headers, to_addr} => {
+                                self.process_headers_msg(headers, to_addr).await?;
+                            },
+                            Messages::Block{block} => {
+                                self.process_block_header(block.get_header()).await?;
+                            },
+                            Messages::Payments{address, payments, to_addr} => {
+                                self.process_payments_msg(address, payments, to_addr);
+                            },
+                            // 全节点之间的消息，轻节点无需处理
+                            _ => {},
+                        }
+                    }
+                },
+                event = self.swarm.select_next_some() => {
+                    if let SwarmEvent::NewListenAddr { address, .. } = event {
+                        println!("Listening on {:?}", address);
+                    }
+                }
+            }
+        }
+    }
+}
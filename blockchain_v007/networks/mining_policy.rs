@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use crate::{ChainParams, error::BlockchainError};
+
+/*
+ * 出块策略，以下任一条件满足时节点开始挖新区块：
+ * 1. 内存池中的交易数量达到min_transactions
+ * 2. 距离上次检查经过了interval，且内存池中有交易或允许出空块
+ * 3. 收到手动出块命令Mine
+ */
+#[derive(Debug, Clone)]
+pub struct MiningPolicy {
+    pub interval: Option<Duration>, // 定时出块的间隔，None表示不定时出块
+    pub min_transactions: usize,    // 内存池中交易达到该数量时立即出块，0表示不按交易数量出块
+    pub allow_empty: bool,          // 定时出块时，是否允许出不包含任何交易的空块
+    pub max_block_size: usize,      // 区块模板序列化之后的最大字节数，超过共识规则的上限时以共识规则为准
+    pub coinbase_data: Vec<u8>,     // 写入coinbase交易的自定义数据
+    pub miner_address: Option<String>,  // 挖矿奖励的接收地址，未指定时使用创世块的地址
+}
+
+impl Default for MiningPolicy {
+    fn default() -> Self {
+        Self {
+            interval: None,
+            min_transactions: 4,
+            allow_empty: false,
+            max_block_size: 100_000,
+            coinbase_data: vec![],
+            miner_address: None,
+        }
+    }
+}
+
+/* 触发出块检查的事件 */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiningTrigger {
+    Transactions,   // 内存池中加入了新交易，或上一轮挖矿结束
+    Timer,          // 定时器到期
+    Manual,         // 手动出块命令
+}
+
+impl MiningPolicy {
+    // 检查出块策略的参数，定时出块的间隔不能为0，挖矿奖励的接收地址必须属于当前网络
+    pub fn validate(&self, params: &ChainParams) -> Result<(), BlockchainError> {
+        if self.interval.is_some_and(|interval| interval.is_zero()) {
+            return Err(BlockchainError::InvalidMiningPolicy("interval must be greater than zero"));
+        }
+        if self.allow_empty && self.interval.is_none() {
+            return Err(BlockchainError::InvalidMiningPolicy("allow_empty requires an interval"));
+        }
+        if let Some(address) = self.miner_address.as_ref().filter(|address| !params.is_valid_address(address)) {
+            return Err(BlockchainError::InvalidAddress(address.clone()));
+        }
+        Ok(())
+    }
+
+    // 根据触发事件和内存池中的交易数量，判断是否需要出块
+    pub fn should_mine(&self, trigger: MiningTrigger, pending: usize) -> bool {
+        match trigger {
+            MiningTrigger::Transactions => self.min_transactions > 0 && pending >= self.min_transactions,
+            MiningTrigger::Timer => pending > 0 || self.allow_empty,
+            MiningTrigger::Manual => true,
+        }
+    }
+}
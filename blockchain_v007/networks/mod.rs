@@ -1,10 +1,14 @@
 mod behaviour;
 mod node;
+mod light_node;
 mod command;
+mod mining_policy;
 
 pub use behaviour::*;
 pub use node::*;
+pub use light_node::LightNode;
 pub use command::*;
+pub use mining_policy::*;
 
 use once_cell::sync::Lazy;
 use tokio::sync::{mpsc, Mutex};
@@ -25,12 +29,10 @@ use libp2p::{
 
 static ID_KEYS: Lazy<Keypair> = Lazy::new(Keypair::generate_ed25519);
 static PEER_ID: Lazy<PeerId> = Lazy::new(|| PeerId::from(ID_KEYS.public()));
-static BLOCK_TOPIC: Lazy<Topic> = Lazy::new(|| Topic::new("blocks"));
-static TRANX_TOPIC: Lazy<Topic> = Lazy::new(|| Topic::new("tranxs"));
 
 static WALLET_MAP: Lazy<Arc<Mutex<HashMap<String, String>>>> = Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
-async fn create_swarm(topics: Vec<Topic>, msg_sender: mpsc::UnboundedSender<Messages>) -> Result<Swarm<BlockchainBehaviour>> {
+async fn create_swarm(topics: Vec<Topic>, msg_sender: mpsc::UnboundedSender<(PeerId, Messages)>) -> Result<Swarm<BlockchainBehaviour>> {
     println!("Local peer id: {:?}", *PEER_ID);
 
     let noise_keys = noise::Keypair::<noise::X25519Spec>::new().into_authentic(&ID_KEYS)?;
@@ -1,43 +1,89 @@
-use std::sync::Arc;
+use std::{sync::{Arc, atomic::{AtomicBool, Ordering}}, time::Duration};
 use once_cell::sync::Lazy;
 use futures::StreamExt;
-use libp2p::{Swarm, swarm::SwarmEvent, PeerId};
+use chrono::Utc;
+use libp2p::{Swarm, swarm::SwarmEvent, PeerId, gossipsub::IdentTopic as Topic};
 use anyhow::Result;
 use tokio::{
     io::{BufReader, stdin, AsyncBufReadExt}, 
     sync::mpsc
 };
-use tracing::{error};
-use crate::{Blockchain, BlockchainBehaviour, KVStorage, RocksDb, UTXOSet, Commands, Messages, Block, Wallets, Transaction, MemoryPool};
+use tracing::{error, info};
+use rustc_serialize::hex::{ToHex, FromHex};
+use crate::{Amount, Blockchain, BlockchainBehaviour, ChainParams, ConsensusParams, KVStorage, RocksDb, UTXOSet, Commands, Messages, Block, Wallets, Transaction, TimeLock, Htlc, MemoryPool, OrphanPool, ChainUpdate, MiningPolicy, create_consensus, MiningTrigger, MAX_HEADERS_PER_MSG, address_to_pub_key_hash, utils::{random_bytes, sha256_digest}, error::BlockchainError};
 
-use super::{create_swarm, BLOCK_TOPIC, TRANX_TOPIC, PEER_ID, WALLET_MAP};
+use super::{create_swarm, PEER_ID, WALLET_MAP};
 
-/* 内存池中的交易到达阈值, 触发矿工挖新区块 */
-pub const TRANSACTION_THRESHOLD: usize = 4;
 /* 交易内存池 */
 static GLOBAL_MEMORY_POOL: Lazy<MemoryPool> = Lazy::new(|| MemoryPool::new());
 
-// 本机矿工地址，后续可以改为配置文件
-static MINER_ADDRESS: &str = "1GMXdoTqE4wfG1kdxDDkYz4qNr7x3dMG8b";
-
 // 增加网络功能后，不再new blockchain，而是仅new一个node
 pub struct Node<T = RocksDb> {
     bc: Blockchain<T>,      // 每个node包含一个本地区块链
     utxos: UTXOSet<T>,      // 每个node包含一个未消费账户集合
-    msg_receiver: mpsc::UnboundedReceiver<Messages>,    // 消息接收端
+    msg_receiver: mpsc::UnboundedReceiver<(PeerId, Messages)>,  // 消息接收端，同时接收转发该消息的节点
     swarm: Swarm<BlockchainBehaviour>,      // rust-libp2p的swarm
+    block_topic: Topic,     // 当前网络广播区块的topic
+    tranx_topic: Topic,     // 当前网络广播交易的topic
+    orphans: OrphanPool,    // 父区块尚未到达的孤块
+    policy: MiningPolicy,   // 出块策略
+    mining_cancel: Option<Arc<AtomicBool>>,     // 正在进行的挖矿任务的取消标志
+    mined_sender: mpsc::UnboundedSender<Option<Block>>,     // 挖矿结果发送端
+    mined_receiver: mpsc::UnboundedReceiver<Option<Block>>, // 挖矿结果接收端
 }
 
 impl<T: KVStorage> Node<T> {
     // 异步构造函数
     pub async fn new(storage: Arc<T>) -> Result<Self> {
+        Self::with_params(storage, ChainParams::default(), MiningPolicy::default()).await
+    }
+
+    // 异步构造函数，指定出块策略
+    pub async fn with_policy(storage: Arc<T>, policy: MiningPolicy) -> Result<Self> {
+        Self::with_params(storage, ChainParams::default(), policy).await
+    }
+
+    // 异步构造函数，指定网络参数和出块策略
+    pub async fn with_params(storage: Arc<T>, params: ChainParams, mut policy: MiningPolicy) -> Result<Self> {
+        params.validate()?;
+        policy.validate(&params)?;
+
+        // 权威证明网络中，使用本地钱包中的验证者私钥签名区块，优先使用出块策略指定的地址，出块奖励发给该验证者
+        let signer = match &params.consensus {
+            ConsensusParams::ProofOfAuthority { validators } => {
+                let wallets = Wallets::new()?;
+                policy.miner_address.iter()
+                    .chain(validators.iter())
+                    .filter(|address| validators.contains(address))
+                    .find_map(|address| wallets.get_wallet(address).map(|wallet| (address.clone(), wallet.clone())))
+            },
+            _ => None,
+        };
+        if let Some((address, _)) = &signer {
+            policy.miner_address = Some(address.clone());
+        }
+        let consensus = create_consensus(&params, signer.map(|(_, wallet)| wallet));
+        if !consensus.can_seal() {
+            info!("This node is not a validator, mining is disabled");
+        }
+
         let (msg_sender, msg_receiver) = mpsc::unbounded_channel();
+        let (mined_sender, mined_receiver) = mpsc::unbounded_channel();
+        let block_topic = Topic::new(params.block_topic.as_str());
+        let tranx_topic = Topic::new(params.tranx_topic.as_str());
 
         Ok(Self {
-            bc: Blockchain::new(storage.clone()),
-            utxos: UTXOSet::new(storage),
+            utxos: UTXOSet::with_maturity(storage.clone(), params.coinbase_maturity),
+            bc: Blockchain::with_consensus(storage, params, consensus),
             msg_receiver,
-            swarm: create_swarm(vec![BLOCK_TOPIC.clone(), TRANX_TOPIC.clone()], msg_sender).await?,
+            swarm: create_swarm(vec![block_topic.clone(), tranx_topic.clone()], msg_sender).await?,
+            block_topic,
+            tranx_topic,
+            orphans: OrphanPool::default(),
+            policy,
+            mining_cancel: None,
+            mined_sender,
+            mined_receiver,
         })
     }
 
@@ -54,49 +100,152 @@ impl<T: KVStorage> Node<T> {
         // 封装本节点的区块高度
         let version = Messages::Version { 
             best_height: self.bc.get_height(), 
+            timestamp: Utc::now().timestamp(),
             from_addr: PEER_ID.to_string(),
         };
                     
         let line = serde_json::to_vec(&version)?;
-        // 广播该消息，通过BLOCK_TOPIC channel
-        self.swarm.behaviour_mut().gossipsub
-            .publish(BLOCK_TOPIC.clone(), line).unwrap();
+        // 广播该消息，通过block_topic channel
+        if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(self.block_topic.clone(), line) {
+            error!("Publish version error: {:?}", e);
+        }
         Ok(())
     }
 
     // 异步转账并可能触发挖矿
-    async fn transfer(&mut self, from: &str, to: &str, amount: i32) -> Result<()> {
-        // 首先完成转账
-        let tx = Transaction::new_utxo_transaction(from, to, amount, &self.utxos, &self.bc);
+    async fn transfer(&mut self, from: &str, to: &str, amount: Amount, fee: Amount, lock: TimeLock) -> Result<()> {
+        // 首先完成转账，余额不足或者钱包不存在时只输出错误，节点继续运行
+        let tx = match Transaction::new_utxo_transaction_with_lock(from, to, amount, fee, lock, &self.utxos, &self.bc) {
+            Ok(tx) => tx,
+            Err(e) => {
+                error!("Transfer failed: {}", e);
+                return Ok(());
+            },
+        };
+        self.send_transaction(tx)
+    }
+
+    /*
+     * 将本地签名完成的交易加入mempool并广播
+     * 交易暂时无法通过验证时只输出交易内容：多重签名地址的交易签名不足时，交给其他参与者使用Sign命令
+     * 继续签名；时间锁尚未到期时，由持有者保存，到期后使用Send命令发送
+     */
+    fn send_transaction(&mut self, tx: Transaction) -> Result<()> {
+        if let Err(e) = self.bc.verify_transaction(&tx) {
+            println!("Transaction {} is not ready to send: {}", tx.get_id(), e);
+            println!("{}", serde_json::to_string(&tx)?);
+            return Ok(());
+        }
+
+        if let Err(e) = Self::check_conflict(&tx) {
+            error!("Reject transaction {}: {}", tx.get_id(), e);
+            return Ok(());
+        }
+
         // 将交易加入memory pool
-        // TODO: 需要检查mempool中交易是否双花
-        GLOBAL_MEMORY_POOL.add(tx);
+        GLOBAL_MEMORY_POOL.add(tx.clone());
+
+        // 广播交易，其他节点也可以将其打包
+        let tx_msg = Messages::Transaction { transaction: tx };
+        let line = serde_json::to_vec(&tx_msg)?;
+        if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(self.tranx_topic.clone(), line) {
+            error!("Publish transaction error: {:?}", e);
+        }
 
         // 出新块
-        if GLOBAL_MEMORY_POOL.len() >= TRANSACTION_THRESHOLD { 
-            // coinbase transaction必须是block中第一条交易
-            let coinbase_tx = Transaction::new_coinbase(MINER_ADDRESS);
-            let mut txs = GLOBAL_MEMORY_POOL.get_all();
-            txs.insert(0, coinbase_tx);
+        self.try_mining(MiningTrigger::Transactions)
+    }
 
-            let block = self.bc.mining(&txs);
-            self.utxos.reindex(&self.bc).unwrap();
+    // 处理其他节点广播的交易，验证通过后加入mempool
+    async fn process_transaction_msg(&mut self, tx: Transaction) -> Result<()> {
+        if GLOBAL_MEMORY_POOL.containes(&tx.get_id()) {
+            return Ok(());
+        }
+        if let Err(e) = self.bc.verify_transaction(&tx).and_then(|_| Self::check_conflict(&tx)) {
+            error!("Reject transaction {} from peer: {}", tx.get_id(), e);
+            return Ok(());
+        }
+        GLOBAL_MEMORY_POOL.add(tx);
+        self.try_mining(MiningTrigger::Transactions)
+    }
 
-            // 从内存池中移除交易
-            for tx in &txs[1..] {
+    // mempool中的交易不能花费同一个输出，先到达的交易优先
+    fn check_conflict(tx: &Transaction) -> Result<(), BlockchainError> {
+        match GLOBAL_MEMORY_POOL.find_conflict(tx) {
+            Some((txid, vout)) => Err(BlockchainError::DoubleSpend(txid, vout)),
+            None => Ok(()),
+        }
+    }
+
+    /*
+     * 根据出块策略判断是否需要出块，且当前没有挖矿任务时，在后台线程中挖新区块，
+     * 挖矿期间节点仍然可以处理命令和网络消息
+     */
+    fn try_mining(&mut self, trigger: MiningTrigger) -> Result<()> {
+        // 不能封装区块的节点（例如没有验证者私钥的权威证明节点）不挖矿，否则封装失败后会反复重试
+        if self.mining_cancel.is_some() || self.bc.get_tip().is_empty() || !self.bc.get_consensus().can_seal() {
+            return Ok(());
+        }
+        if !self.policy.should_mine(trigger, GLOBAL_MEMORY_POOL.len()) {
+            return Ok(());
+        }
+
+        // 丢弃在当前主链上已经无效的交易
+        let mut txs = vec![];
+        for tx in GLOBAL_MEMORY_POOL.get_all() {
+            if self.bc.verify_transaction(&tx).is_ok() {
+                txs.push(tx);
+            } else {
                 GLOBAL_MEMORY_POOL.remove(tx.get_id().as_str());
             }
-
-            // 广播新块
-            let block_store = Messages::Block { block };
-            let line = serde_json::to_vec(&block_store)?;
-            self.swarm.behaviour_mut().gossipsub
-                .publish(BLOCK_TOPIC.clone(), line).unwrap();        
         }
+        // 按手续费率挑选交易，手续费高的交易优先打包
+        let miner_address = self.policy.miner_address.clone()
+            .unwrap_or_else(|| self.bc.get_params().genesis.address.clone());
+        let mut block = self.bc.new_block_template(&miner_address, &txs, self.policy.max_block_size, &self.policy.coinbase_data)?;
 
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.mining_cancel = Some(cancel.clone());
+        let consensus = self.bc.get_consensus();
+        let mined_sender = self.mined_sender.clone();
+        tokio::task::spawn_blocking(move || {
+            let mined = consensus.seal(&mut block, &cancel);
+            mined_sender.send(if mined { Some(block) } else { None }).ok();
+        });
         Ok(())
     }
 
+    // 主链的tip发生变化，正在挖的区块已经过时，取消挖矿任务
+    fn cancel_mining(&self) {
+        if let Some(cancel) = &self.mining_cancel {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // 处理后台挖矿任务的结果：将新块加入区块链并广播，然后尝试开始下一轮挖矿
+    async fn process_mined_block(&mut self, block: Option<Block>) -> Result<()> {
+        self.mining_cancel = None;
+
+        if let Some(block) = block {
+            match self.bc.add_block(block.clone()) {
+                Ok(update) => {
+                    // UTXO集合在连接区块时已经增量更新
+                    self.apply_chain_update(update);
+
+                    // 广播新块
+                    let block_store = Messages::Block { block };
+                    let line = serde_json::to_vec(&block_store)?;
+                    if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(self.block_topic.clone(), line) {
+                        error!("Publish mined block error: {:?}", e);
+                    }
+                },
+                Err(e) => error!("Mined block is rejected: {}", e),
+            }
+        }
+
+        self.try_mining(MiningTrigger::Transactions)
+    }
+
     // 处理区块版本（高度），如果本节点区块高度更大，则广播整个区块链
     async fn process_version_msg(&mut self, best_height: usize, from_addr: String) -> Result<()> {
         if self.bc.get_height() > best_height {
@@ -106,8 +255,9 @@ impl<T: KVStorage> Node<T> {
                 to_addr: from_addr,
             };
             let msg = serde_json::to_vec(&blocks)?;
-            self.swarm.behaviour_mut().gossipsub
-                .publish(BLOCK_TOPIC.clone(), msg).unwrap();
+            if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(self.block_topic.clone(), msg) {
+                error!("Send blocks error: {:?}", e);
+            }
             }
         Ok(())
     }
@@ -115,22 +265,176 @@ impl<T: KVStorage> Node<T> {
     // 处理区块链，若接收到更长的区块链，则替换本地区块链
     async fn process_blocks_msg(&mut self, blocks: Vec<Block>, to_addr: String, height: usize) -> Result<()> {
         if PEER_ID.to_string() == to_addr && self.bc.get_height() < height {
+            // 先根据区块头确认哪些区块是假定有效区块的祖先，连接这些区块时跳过签名验证
+            let headers: Vec<_> = blocks.iter().map(|block| block.get_header()).collect();
+            self.bc.add_assumed_valid_headers(&headers);
+
+            let mut tip_changed = false;
             for block in blocks {
-                self.bc.add_block(block)?;
+                // 已存在的区块会被跳过，分叉的区块存储在侧链上，必要时触发重组
+                let hash = block.get_hash();
+                match self.bc.add_block(block) {
+                    Ok(update) => {
+                        tip_changed |= self.apply_chain_update(update);
+                        tip_changed |= self.connect_orphans(&hash);
+                    },
+                    Err(e) => {
+                        // 遇到非法区块，丢弃其后的所有区块
+                        error!("Reject blocks from peer: {}", e);
+                        break;
+                    },
+                }
             }
 
-            self.utxos.reindex(&self.bc).unwrap();
+            if tip_changed {
+                self.cancel_mining();
+            }
         }
         Ok(())
     }
 
     // 处理区块，接收到其他节点挖到的新块
     pub async fn process_block_msg(&mut self, block: Block) -> Result<()> {
-        self.bc.add_block(block)?;
-        self.utxos.reindex(&self.bc).unwrap();
+        let hash = block.get_hash();
+        let prev_hash = block.get_prev_hash();
+
+        // 父区块未知，暂存为孤块，并向其他节点请求缺失的祖先区块
+        if !prev_hash.is_empty() && self.bc.find_block(&prev_hash).is_none() {
+            // 孤块池只接受通过上下文无关检查（区块hash、封装、交易）的区块，防止伪造的区块占满孤块池
+            if let Err(e) = self.bc.check_block(&block) {
+                error!("Reject orphan block {}: {}", hash, e);
+                return Ok(());
+            }
+            if !self.orphans.contains(&hash) {
+                self.orphans.add(block);
+                let missing = self.orphans.get_missing_ancestor(&hash);
+                self.request_block(missing).await?;
+            }
+            return Ok(());
+        }
+
+        let mut tip_changed = false;
+        match self.bc.add_block(block) {
+            Ok(update) => {
+                tip_changed |= self.apply_chain_update(update);
+                tip_changed |= self.connect_orphans(&hash);
+            },
+            // 非法区块直接丢弃，不能因为其他节点的恶意消息导致本节点退出
+            Err(e) => error!("Reject block from peer: {}", e),
+        }
+
+        if tip_changed {
+            self.cancel_mining();
+        }
+        Ok(())
+    }
+
+    // 父区块加入区块链之后，递归地连接孤块池中它的所有后代区块，返回主链是否发生了变化
+    fn connect_orphans(&mut self, parent_hash: &str) -> bool {
+        let mut tip_changed = false;
+        let mut parents = vec![parent_hash.to_string()];
+        while let Some(parent) = parents.pop() {
+            for block in self.orphans.take_children(&parent) {
+                let hash = block.get_hash();
+                match self.bc.add_block(block) {
+                    Ok(update) => {
+                        tip_changed |= self.apply_chain_update(update);
+                        parents.push(hash);
+                    },
+                    Err(e) => error!("Reject orphan block: {}", e),
+                }
+            }
+        }
+        tip_changed
+    }
+
+    // 向其他节点请求一个缺失的区块
+    async fn request_block(&mut self, hash: String) -> Result<()> {
+        let get_block = Messages::GetBlock { hash };
+        let msg = serde_json::to_vec(&get_block)?;
+        // 重复的请求会被gossipsub当作重复消息拒绝，不影响节点运行
+        if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(self.block_topic.clone(), msg) {
+            error!("Request block error: {:?}", e);
+        }
+        Ok(())
+    }
+
+    // 处理其他节点的区块请求，如果本地存储了该区块，则广播出去
+    async fn process_get_block_msg(&mut self, hash: String) -> Result<()> {
+        if let Some(block) = self.bc.find_block(&hash) {
+            let msg = serde_json::to_vec(&Messages::Block { block })?;
+            if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(self.block_topic.clone(), msg) {
+                error!("Send block error: {:?}", e);
+            }
+        }
+        Ok(())
+    }
+
+    // 处理轻节点的区块头同步请求，发送分叉点之后的区块头
+    async fn process_get_headers_msg(&mut self, locator: Vec<String>, from_addr: String) -> Result<()> {
+        let headers = Messages::Headers {
+            headers: self.bc.get_headers_after(&locator, MAX_HEADERS_PER_MSG),
+            to_addr: from_addr,
+        };
+        let msg = serde_json::to_vec(&headers)?;
+        if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(self.block_topic.clone(), msg) {
+            error!("Send headers error: {:?}", e);
+        }
         Ok(())
     }
 
+    // 处理轻节点的支付查询请求，发送支付给该地址的交易及其Merkle证明
+    async fn process_get_payments_msg(&mut self, address: String, from_addr: String) -> Result<()> {
+        if !self.bc.get_params().is_valid_address(&address) {
+            error!("Invalid address from light node: {}", address);
+            return Ok(());
+        }
+
+        let payments = Messages::Payments {
+            payments: self.bc.find_payments(&address_to_pub_key_hash(&address)),
+            address,
+            to_addr: from_addr,
+        };
+        let msg = serde_json::to_vec(&payments)?;
+        if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(self.block_topic.clone(), msg) {
+            error!("Send payments error: {:?}", e);
+        }
+        Ok(())
+    }
+
+    // 根据主链的变化同步mempool，返回主链是否发生了变化
+    fn apply_chain_update(&self, update: ChainUpdate) -> bool {
+        match update {
+            ChainUpdate::Existed | ChainUpdate::SideChain => false,
+            ChainUpdate::Extended(block) => {
+                Self::remove_mined_txs(&block);
+                true
+            },
+            ChainUpdate::Reorganized { disconnected, connected } => {
+                // 被断开区块中的交易放回mempool，前提是在新的主链上仍然有效，且不与mempool中的交易冲突
+                for block in &disconnected {
+                    for tx in block.get_transactions().iter().skip(1) {
+                        if self.bc.verify_transaction(tx).is_ok() && Self::check_conflict(tx).is_ok() {
+                            GLOBAL_MEMORY_POOL.add(tx.clone());
+                        }
+                    }
+                }
+                for block in &connected {
+                    Self::remove_mined_txs(block);
+                }
+                println!("Chain reorganized: {} blocks disconnected, {} blocks connected", disconnected.len(), connected.len());
+                true
+            },
+        }
+    }
+
+    // 从mempool中移除已经打包进区块的交易
+    fn remove_mined_txs(block: &Block) {
+        for tx in block.get_transactions() {
+            GLOBAL_MEMORY_POOL.remove(tx.get_id().as_str());
+        }
+    }
+
     // 启动服务，入口函数，主逻辑
     pub async fn start(&mut self) -> Result<()> {
         // 监听端口
@@ -138,6 +442,10 @@ impl<T: KVStorage> Node<T> {
                 
         // 监听命令行输入
         let mut stdin = BufReader::new(stdin()).lines();
+
+        // 定时出块的定时器，未配置定时出块时不会触发
+        let interval = self.policy.interval;
+        let mut mining_timer = tokio::time::interval(interval.unwrap_or(Duration::from_secs(3600)));
                 
         loop {
             tokio::select! {
@@ -149,10 +457,15 @@ impl<T: KVStorage> Node<T> {
                     match command {
                         Ok(cmd) => match cmd {
                             // 创世块命令，当前区块链必须为空
-                            Commands::Genesis(addr) => {
+                            // 创世块由网络参数确定，命令中的地址只能为空，或者与网络参数中的创世块地址一致
+                            Commands::Genesis(address) => {
+                                let genesis_address = &self.bc.get_params().genesis.address;
+                                if !address.is_empty() && &address != genesis_address {
+                                    println!("Genesis address of this network is {}, not {}", genesis_address, address);
+                                    continue;
+                                }
                                 if self.bc.get_tip().is_empty() {
-                                    self.bc.create_genesis_block(addr.as_str());
-                                    self.utxos.reindex(&self.bc)?;
+                                    self.bc.create_genesis_block()?;
                                     println!("Genesis block was created success!");
                                 } else {
                                     println!("Already exists blockchain, don't need genesis block!");
@@ -164,6 +477,20 @@ impl<T: KVStorage> Node<T> {
                                 self.bc.blocks_info();
                                 println!("tip: {}", self.bc.get_tip());
                                 println!("height: {}", self.bc.get_height());
+                                println!("hash rate: {} H/s", self.bc.get_consensus().get_hash_rate());
+                            },
+                            // 输出代币的发行情况
+                            Commands::Supply => {
+                                let height = self.bc.get_height();
+                                println!("height: {}", height);
+                                println!("circulating supply: {}", self.bc.circulating_supply(height));
+                                println!("next block subsidy: {}", self.bc.get_subsidy(height + 1));
+                            },
+                            // 输出所有软分叉部署的状态
+                            Commands::Deployments => {
+                                for deployment in &self.bc.get_params().versionbits.deployments {
+                                    println!("{} (bit {}): {:?}", deployment.name, deployment.bit, self.bc.get_deployment_state(&deployment.name));
+                                }
                             },
                             // 同步区块链状态
                             Commands::Sync(_) => {
@@ -174,7 +501,7 @@ impl<T: KVStorage> Node<T> {
                                 WALLET_MAP.lock().await.entry(name.clone())
                                     .or_insert_with(|| {
                                     let mut wallets = Wallets::new().unwrap();
-                                    let addr = wallets.create_wallet();
+                                    let addr = wallets.create_wallet(self.bc.get_params().address_version);
                                     println!("{}'s address is {}", name, addr);
                                     addr
                                 });
@@ -184,11 +511,15 @@ impl<T: KVStorage> Node<T> {
                                 println!("{}'s address is {}", name, WALLET_MAP.clone().lock().await.get(&name).unwrap());
                             },
                             Commands::GetBalance(address) => {
-                                let wallets = Wallets::new().unwrap();
-                                let pub_key_hash = wallets.get_wallet(address.as_str()).unwrap().get_public_key();
+                                if !self.bc.get_params().is_valid_address(&address) {
+                                    println!("Invalid address: {}", address);
+                                    continue;
+                                }
+                                let pub_key_hash = address_to_pub_key_hash(&address);
 
-                                let balance = self.utxos.get_balance(pub_key_hash);
-                                println!("Balance of {}: {}", address, balance);
+                                let balance = self.utxos.get_balance(&pub_key_hash);
+                                let immature = self.utxos.get_immature_balance(&pub_key_hash);
+                                println!("Balance of {}: {}, immature: {}", address, balance, immature);
                             },
                             // 列出本地钱包中的所有地址
                             Commands::ListAddresses => {
@@ -197,9 +528,152 @@ impl<T: KVStorage> Node<T> {
                                     println!("{}", address)
                                 }
                             },
+                            // 输出本地钱包的公钥，用于创建多重签名地址
+                            Commands::PublicKey(address) => {
+                                match Wallets::new()?.get_wallet(&address) {
+                                    Some(wallet) => println!("{}", wallet.get_public_key().to_hex()),
+                                    None => println!("Wallet {} is not found", address),
+                                }
+                            },
+                            // 创建多重签名地址，所有参与者使用相同的公钥顺序创建，得到相同的地址
+                            Commands::CreateMultisig{required, public_keys} => {
+                                let pub_keys = match public_keys.iter().map(|pub_key| pub_key.from_hex()).collect::<Result<Vec<_>, _>>() {
+                                    Ok(pub_keys) => pub_keys,
+                                    Err(e) => {
+                                        println!("Invalid public key: {}", e);
+                                        continue;
+                                    },
+                                };
+                                let mut wallets = Wallets::new()?;
+                                match wallets.create_multisig(required, &pub_keys, self.bc.get_params().script_address_version) {
+                                    Ok(address) => println!("multisig address is {}", address),
+                                    Err(e) => println!("{}", e),
+                                }
+                            },
+                            // 使用本地钱包对其他参与者发来的多重签名交易继续签名，签名足够后发送
+                            Commands::Sign(mut tx) => {
+                                if let Err(e) = tx.sign(&self.bc, &Wallets::new()?) {
+                                    println!("Sign transaction {} failed: {}", tx.get_id(), e);
+                                    continue;
+                                }
+                                self.send_transaction(tx)?;
+                            },
+                            // 创建哈希时间锁合约，没有指定哈希时生成新的原像，原像由发起方保存，领取对方的合约时公开
+                            Commands::HtlcCreate{from, receiver, amount, fee, timeout, hash} => {
+                                let (amount, fee) = match (amount.parse::<Amount>(), parse_fee(fee)) {
+                                    (Ok(amount), Ok(fee)) => (amount, fee),
+                                    (Err(e), _) | (_, Err(e)) => {
+                                        println!("{}", e);
+                                        continue;
+                                    },
+                                };
+                                let (preimage, hash_lock) = match hash.map(|hash| hash.from_hex()) {
+                                    Some(Ok(hash_lock)) => (None, hash_lock),
+                                    Some(Err(e)) => {
+                                        println!("Invalid hash: {}", e);
+                                        continue;
+                                    },
+                                    None => {
+                                        let preimage = random_bytes(32);
+                                        let hash_lock = sha256_digest(&preimage);
+                                        (Some(preimage), hash_lock)
+                                    },
+                                };
+                                let tx = Htlc::new(hash_lock.clone(), &receiver, &from, timeout, self.bc.get_params())
+                                    .and_then(|htlc| Transaction::new_htlc_transaction(&from, &htlc, amount, fee, &self.utxos, &self.bc));
+                                match tx {
+                                    Ok(tx) => {
+                                        if let Some(preimage) = preimage {
+                                            println!("preimage: {}", preimage.to_hex());
+                                        }
+                                        println!("hash: {}", hash_lock.to_hex());
+                                        println!("contract: {} 0", tx.get_id());
+                                        self.send_transaction(tx)?;
+                                    },
+                                    Err(e) => error!("Create HTLC failed: {}", e),
+                                }
+                            },
+                            // 凭原像领取哈希时间锁合约
+                            Commands::HtlcClaim{txid, vout, preimage, to, fee} => {
+                                let fee = match parse_fee(fee) {
+                                    Ok(fee) => fee,
+                                    Err(e) => {
+                                        println!("{}", e);
+                                        continue;
+                                    },
+                                };
+                                let preimage = match preimage.from_hex() {
+                                    Ok(preimage) => preimage,
+                                    Err(e) => {
+                                        println!("Invalid preimage: {}", e);
+                                        continue;
+                                    },
+                                };
+                                match Transaction::new_htlc_claim(&txid, vout, &preimage, &to, fee, &self.bc) {
+                                    Ok(tx) => self.send_transaction(tx)?,
+                                    Err(e) => error!("Claim HTLC failed: {}", e),
+                                }
+                            },
+                            // 合约超时后退款，超时之前只输出交易，到期后使用Send命令发送
+                            Commands::HtlcRefund{txid, vout, to, fee} => {
+                                let fee = match parse_fee(fee) {
+                                    Ok(fee) => fee,
+                                    Err(e) => {
+                                        println!("{}", e);
+                                        continue;
+                                    },
+                                };
+                                match Transaction::new_htlc_refund(&txid, vout, &to, fee, &self.bc) {
+                                    Ok(tx) => self.send_transaction(tx)?,
+                                    Err(e) => error!("Refund HTLC failed: {}", e),
+                                }
+                            },
+                            // 查找对方领取合约时公开的原像，用于领取另一条网络上使用相同哈希的合约
+                            Commands::HtlcPreimage(hash) => {
+                                match hash.from_hex().ok().and_then(|hash_lock| self.bc.find_preimage(&hash_lock)) {
+                                    Some(preimage) => println!("preimage: {}", preimage.to_hex()),
+                                    None => println!("Preimage of {} is not revealed", hash),
+                                }
+                            },
+                            // 发送已经签名完成的交易，例如到期的时间锁交易
+                            Commands::Send(tx) => {
+                                self.send_transaction(tx)?;
+                            },
+                            // 生成交易的Merkle包含证明，交给第三方验证
+                            Commands::Proof(txid) => {
+                                match self.bc.find_merkle_proof(&txid) {
+                                    Some((header, proof)) => {
+                                        println!("header: {}", serde_json::to_string(&header)?);
+                                        println!("proof: {}", serde_json::to_string(&proof)?);
+                                        println!("valid: {}", proof.verify(&header.get_txs_hash()));
+                                    },
+                                    None => println!("Transaction {} is not found in the chain", txid),
+                                }
+                            },
+                            // 手动出块
+                            Commands::Mine => {
+                                if self.mining_cancel.is_some() {
+                                    println!("Already mining, please wait");
+                                } else if self.bc.get_tip().is_empty() {
+                                    println!("Blockchain is empty, create genesis block first");
+                                } else {
+                                    self.try_mining(MiningTrigger::Manual)?;
+                                }
+                            },
                             // 转账交易
-                            Commands::Trans{from, to, amount} => {
-                                self.transfer(&from, &to, amount.parse::<i32>().unwrap()).await?;
+                            Commands::Trans{from, to, amount, fee, lock_time, sequence} => {
+                                let (amount, fee) = match (amount.parse::<Amount>(), parse_fee(fee)) {
+                                    (Ok(amount), Ok(fee)) => (amount, fee),
+                                    (Err(e), _) | (_, Err(e)) => {
+                                        println!("{}", e);
+                                        continue;
+                                    },
+                                };
+                                let mut lock = lock_time.map_or_else(TimeLock::default, TimeLock::new_absolute);
+                                if let Some(sequence) = sequence {
+                                    lock.sequence = sequence;
+                                }
+                                self.transfer(&from, &to, amount, fee, lock).await?;
                             },
                         },
                         Err(e) => {
@@ -210,11 +684,13 @@ impl<T: KVStorage> Node<T> {
                 },
                 // messages是通过P2P网络收到的消息
                 messages = self.msg_receiver.recv() => {
-                    if let Some(msg) = messages {
+                    if let Some((peer, msg)) = messages {
                         // Messages是一个enum类型
                         match msg {
                             // 收到其他节点广播的区块链版本（高度）消息
-                            Messages::Version{best_height, from_addr} => {
+                            Messages::Version{best_height, timestamp, from_addr} => {
+                                // 时间样本按libp2p连接上的节点记录，消息中的from_addr由对方自行填写，不能用于区分节点
+                                self.bc.get_time_data().add_sample(&peer.to_string(), timestamp);
                                 self.process_version_msg(best_height, from_addr).await?;
                             },
                             // 收到其他节点广播的区块链完整信息
@@ -224,10 +700,38 @@ impl<T: KVStorage> Node<T> {
                             // 收到其他节点广播的挖到的新块
                             Messages::Block{block} => {
                                 self.process_block_msg(block).await?;
-                            }
+                            },
+                            // 收到其他节点广播的交易
+                            Messages::Transaction{transaction} => {
+                                self.process_transaction_msg(transaction).await?;
+                            },
+                            // 收到其他节点的区块请求
+                            Messages::GetBlock{hash} => {
+                                self.process_get_block_msg(hash).await?;
+                            },
+                            // 收到轻节点的区块头同步请求
+                            Messages::GetHeaders{locator, from_addr} => {
+                                self.process_get_headers_msg(locator, from_addr).await?;
+                            },
+                            // 收到轻节点的支付查询请求
+                            Messages::GetPayments{address, from_addr} => {
+                                self.process_get_payments_msg(address, from_addr).await?;
+                            },
+                            // 发送给轻节点的消息，全节点无需处理
+                            Messages::Headers{..} | Messages::Payments{..} => {},
                         }
                     }
                 },
+                // 定时出块
+                _ = mining_timer.tick(), if interval.is_some() => {
+                    self.try_mining(MiningTrigger::Timer)?;
+                },
+                // 后台挖矿任务的结果
+                mined = self.mined_receiver.recv() => {
+                    if let Some(block) = mined {
+                        self.process_mined_block(block).await?;
+                    }
+                },
                 event = self.swarm.select_next_some() => { 
                     if let SwarmEvent::NewListenAddr { address, .. } = event { 
                         println!("Listening on {:?}", address); 
@@ -237,3 +741,8 @@ impl<T: KVStorage> Node<T> {
         }
     }
 }
+
+// 解析命令中的手续费，未指定时为0
+fn parse_fee(fee: Option<String>) -> Result<Amount, BlockchainError> {
+    fee.map_or(Ok(Amount::ZERO), |fee| fee.parse())
+}
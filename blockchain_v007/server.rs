@@ -1,22 +1,62 @@
-use std::{env::{current_dir, self}, sync::Arc};
+use std::{env::{current_dir, self}, sync::Arc, time::Duration};
 use anyhow::Result;
 
-use hungrytiger::{RocksDb, Node};
+use hungrytiger::{RocksDb, Node, ChainParams, MiningPolicy};
 
 /* 以server方式启动节点，监听其他节点的消息 */
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
 
+    // 以--开头的参数是出块策略选项，其余参数按位置解析
+    let (options, args): (Vec<String>, Vec<String>) = env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    let policy = parse_policy(&options)?;
+
     // 第二个参数是区块链存储路径，默认为data目录
     let mut path = String::from("data");
-    if let Some(args) = env::args().nth(1) {
-        path = args;
+    if let Some(arg) = args.first() {
+        path = arg.clone();
     }
 
+    // 第三个参数是网络名称：main、test、regtest、dev或private，默认为main
+    // private网络之后的参数是验证者地址列表
+    let params = match args.get(1) {
+        Some(name) if name == "private" => {
+            let validators: Vec<String> = args.iter().skip(2).cloned().collect();
+            if validators.is_empty() {
+                anyhow::bail!("Private network requires at least one validator address");
+            }
+            ChainParams::private(validators)
+        },
+        Some(name) => ChainParams::from_name(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown network: {}", name))?,
+        None => ChainParams::main(),
+    };
+
     let path = current_dir().unwrap().join(path);
     let db = Arc::new(RocksDb::new(path));
-    let mut node = Node::new(db).await?;
+    let mut node = Node::with_params(db, params, policy).await?;
     node.start().await?;
     Ok(())
 }
+
+/*
+ * 解析出块策略选项，节点启动时检查选项的取值
+ *     --interval=<秒>   定时出块的间隔，必须大于0
+ *     --allow-empty     定时出块时允许出空块，需要同时指定--interval
+ */
+fn parse_policy(options: &[String]) -> Result<MiningPolicy> {
+    let mut policy = MiningPolicy::default();
+    for option in options {
+        match option.split_once('=') {
+            Some(("--interval", secs)) => {
+                let secs: u64 = secs.parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid mining interval: {}", secs))?;
+                policy.interval = Some(Duration::from_secs(secs));
+            },
+            None if option == "--allow-empty" => policy.allow_empty = true,
+            _ => anyhow::bail!("Unknown option: {}", option),
+        }
+    }
+    Ok(policy)
+}
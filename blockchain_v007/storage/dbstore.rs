@@ -2,7 +2,7 @@ use std::{path::Path, collections::HashMap};
 use std::sync::Arc;
 use rocksdb::{{DB, IteratorMode}};
 
-use crate::{Block, KVStorage, error::BlockchainError, utils::{deserialize, serialize}, TIP_KEY, HEIGHT, TABLE_OF_BLOCK, UTXO_SET, TxOutput};
+use crate::{Block, BlockHeader, BlockIndex, KVStorage, error::BlockchainError, utils::{deserialize, serialize}, TIP_KEY, HEIGHT, TABLE_OF_BLOCK, TABLE_OF_BLOCK_INDEX, TABLE_OF_HEADER, HEADER_TIP_KEY, TABLE_OF_BLOCK_UNDO, TABLE_OF_MAIN_CHAIN, TABLE_OF_INVALID_BLOCK, UTXO_SET, UtxoEntry};
 
 /*
  * 数据库实现
@@ -52,6 +52,89 @@ impl KVStorage for RocksDb {
         self.db.flush().ok();
     }
 
+    // 仅存储block，不改变tip，用于侧链上的区块
+    fn put_block(&self, key: &str, block: &Block) {
+        let row_key = Self::get_full_key(TABLE_OF_BLOCK, key);
+        self.db.put(row_key.as_str(), serialize(block).unwrap()).ok();
+        self.db.flush().ok();
+    }
+
+    // 切换区块链的tip和高度，用于区块链重组
+    fn set_tip(&self, key: &str, height: usize) {
+        self.db.put(TIP_KEY, serialize(key).unwrap()).ok();
+        self.db.put(HEIGHT, serialize(&height).unwrap()).ok();
+        self.db.flush().ok();
+    }
+
+    fn get_block_index(&self, key: &str) -> Result<Option<BlockIndex>, BlockchainError> {
+        let name = Self::get_full_key(TABLE_OF_BLOCK_INDEX, key);
+        let result = self.db.get(name)?.map(|v| deserialize::<BlockIndex>(&v));
+        result.map_or(Ok(None), |v| v.map(Some))
+    }
+
+    // 存储区块索引，以该block的hash为key
+    fn put_block_index(&self, key: &str, index: &BlockIndex) {
+        let row_key = Self::get_full_key(TABLE_OF_BLOCK_INDEX, key);
+        self.db.put(row_key.as_str(), serialize(index).unwrap()).ok();
+    }
+
+    fn get_block_undo(&self, key: &str) -> Result<Option<Vec<(String, UtxoEntry)>>, BlockchainError> {
+        let name = Self::get_full_key(TABLE_OF_BLOCK_UNDO, key);
+        let result = self.db.get(name)?.map(|v| deserialize::<Vec<(String, UtxoEntry)>>(&v));
+        result.map_or(Ok(None), |v| v.map(Some))
+    }
+
+    // 存储区块花费的输出，断开区块时据此恢复UTXO集合
+    fn put_block_undo(&self, key: &str, undo: &[(String, UtxoEntry)]) {
+        let row_key = Self::get_full_key(TABLE_OF_BLOCK_UNDO, key);
+        self.db.put(row_key.as_str(), serialize(undo).unwrap()).ok();
+    }
+
+    fn get_main_chain_hash(&self, height: usize) -> Result<Option<String>, BlockchainError> {
+        let name = Self::get_full_key(TABLE_OF_MAIN_CHAIN, &height.to_string());
+        let result = self.db.get(name)?.map(|v| deserialize::<String>(&v));
+        result.map_or(Ok(None), |v| v.map(Some))
+    }
+
+    // 存储主链上高度为height的区块hash，高于tip的记录已经失效
+    fn put_main_chain_hash(&self, height: usize, key: &str) {
+        let row_key = Self::get_full_key(TABLE_OF_MAIN_CHAIN, &height.to_string());
+        self.db.put(row_key.as_str(), serialize(key).unwrap()).ok();
+    }
+
+    fn is_invalid_block(&self, key: &str) -> Result<bool, BlockchainError> {
+        let name = Self::get_full_key(TABLE_OF_INVALID_BLOCK, key);
+        Ok(self.db.get(name)?.is_some())
+    }
+
+    // 标记验证失败的区块，以后收到该区块或其后代区块时直接拒绝
+    fn put_invalid_block(&self, key: &str) {
+        let row_key = Self::get_full_key(TABLE_OF_INVALID_BLOCK, key);
+        self.db.put(row_key.as_str(), serialize(&true).unwrap()).ok();
+    }
+
+    fn get_header(&self, key: &str) -> Result<Option<BlockHeader>, BlockchainError> {
+        let name = Self::get_full_key(TABLE_OF_HEADER, key);
+        let result = self.db.get(name)?.map(|v| deserialize::<BlockHeader>(&v));
+        result.map_or(Ok(None), |v| v.map(Some))
+    }
+
+    // 轻节点存储区块头，以区块hash为key
+    fn put_header(&self, key: &str, header: &BlockHeader) {
+        let row_key = Self::get_full_key(TABLE_OF_HEADER, key);
+        self.db.put(row_key.as_str(), serialize(header).unwrap()).ok();
+    }
+
+    fn get_header_tip(&self) -> Result<Option<String>, BlockchainError> {
+        let result = self.db.get(HEADER_TIP_KEY)?.map(|v| deserialize::<String>(&v));
+        result.map_or(Ok(None), |v| v.map(Some))
+    }
+
+    fn set_header_tip(&self, key: &str) {
+        self.db.put(HEADER_TIP_KEY, serialize(key).unwrap()).ok();
+        self.db.flush().ok();
+    }
+
     // 遍历db中存储的所有block，存储block的key统一以“blocks:”为前缀
     /*
     fn get_block_iter(&self) -> Result<Box<dyn Iterator<Item = Block>>, BlockchainError> {
@@ -61,7 +144,7 @@ impl KVStorage for RocksDb {
     }
     */
 
-    fn get_utxo_set(&self) -> HashMap<String, Vec<TxOutput>> {
+    fn get_utxo_set(&self) -> HashMap<String, Vec<UtxoEntry>> {
         let mut map = HashMap::new();
 
         let prefix = format!("{}:", UTXO_SET);
@@ -71,7 +154,7 @@ impl KVStorage for RocksDb {
             if k.starts_with(prefix.as_bytes()) {
                 let txid = String::from_utf8(k.to_vec()).unwrap();
                 let txid = txid.split(":").collect::<Vec<_>>()[1].into();
-                let outputs = deserialize::<Vec<TxOutput>>(&v.to_vec()).unwrap();
+                let outputs = deserialize::<Vec<UtxoEntry>>(&v.to_vec()).unwrap();
 
                 map.insert(txid, outputs);
             }
@@ -79,18 +162,34 @@ impl KVStorage for RocksDb {
         map
     }
 
+    fn get_utxo(&self, txid: &str) -> Result<Option<Vec<UtxoEntry>>, BlockchainError> {
+        let name = format!("{}:{}", UTXO_SET, txid);
+        let result = self.db.get(name)?.map(|v| deserialize::<Vec<UtxoEntry>>(&v));
+        result.map_or(Ok(None), |v| v.map(Some))
+    }
+
     // 写入utxo账户，基于前缀UTXO_SET
-    fn write_utxo(&self, txid: &str, outs: Vec<TxOutput>) -> Result<(), BlockchainError> {
+    fn write_utxo(&self, txid: &str, outs: Vec<UtxoEntry>) -> Result<(), BlockchainError> {
         let name = format!("{}:{}", UTXO_SET, txid);
         self.db.put(name, serialize(&outs)?)?;
         Ok(())
     }
 
 
-    // 清空utxo集合
+    // 删除一笔交易的所有utxo账户
+    fn delete_utxo(&self, txid: &str) {
+        let name = format!("{}:{}", UTXO_SET, txid);
+        self.db.delete(name).ok();
+    }
+
+    // 清空utxo集合，删除所有以UTXO_SET为前缀的key
     fn clear_utxo_set(&self) {
         let prefix = format!("{}:", UTXO_SET);
-        self.db.delete(prefix).unwrap();
+        for (k, _) in self.db.iterator(IteratorMode::Start) {
+            if k.starts_with(prefix.as_bytes()) {
+                self.db.delete(k).ok();
+            }
+        }
     }
 }
 
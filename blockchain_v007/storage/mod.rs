@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use crate::{Block, error::BlockchainError, TxOutput};
+use crate::{Block, BlockHeader, BlockIndex, error::BlockchainError, UtxoEntry};
 
 mod dbstore;
 
@@ -14,6 +14,18 @@ pub const HEIGHT: &str = "height";
 pub const TABLE_OF_BLOCK: &str = "blocks";
 // UTXO集合的key
 pub const UTXO_SET: &str = "utxos";
+// 存储区块索引（高度、累计工作量）的key
+pub const TABLE_OF_BLOCK_INDEX: &str = "block_index";
+// 轻节点存储区块头的key
+pub const TABLE_OF_HEADER: &str = "headers";
+// 轻节点存储区块头链最后一个区块hash的key
+pub const HEADER_TIP_KEY: &str = "header_tip_hash";
+// 存储区块撤销数据（区块花费的输出）的key
+pub const TABLE_OF_BLOCK_UNDO: &str = "block_undo";
+// 存储主链上每个高度的区块hash的key
+pub const TABLE_OF_MAIN_CHAIN: &str = "main_chain";
+// 存储验证失败的区块及其后代区块hash的key
+pub const TABLE_OF_INVALID_BLOCK: &str = "invalid_blocks";
 
 /*
  * 数据库接口定义
@@ -23,10 +35,27 @@ pub trait KVStorage: Send + Sync + 'static {
     fn get_block(&self, key: &str) -> Result<Option<Block>, BlockchainError>;
     fn get_height(&self) -> Result<Option<usize>, BlockchainError>;
     fn update_blocks(&self, key: &str, block: &Block, height: usize);
+    fn put_block(&self, key: &str, block: &Block);
+    fn set_tip(&self, key: &str, height: usize);
+    fn get_block_index(&self, key: &str) -> Result<Option<BlockIndex>, BlockchainError>;
+    fn put_block_index(&self, key: &str, index: &BlockIndex);
+    fn get_block_undo(&self, key: &str) -> Result<Option<Vec<(String, UtxoEntry)>>, BlockchainError>;
+    fn put_block_undo(&self, key: &str, undo: &[(String, UtxoEntry)]);
+    fn get_main_chain_hash(&self, height: usize) -> Result<Option<String>, BlockchainError>;
+    fn put_main_chain_hash(&self, height: usize, key: &str);
+    fn is_invalid_block(&self, key: &str) -> Result<bool, BlockchainError>;
+    fn put_invalid_block(&self, key: &str);
+
+    fn get_header(&self, key: &str) -> Result<Option<BlockHeader>, BlockchainError>;
+    fn put_header(&self, key: &str, header: &BlockHeader);
+    fn get_header_tip(&self) -> Result<Option<String>, BlockchainError>;
+    fn set_header_tip(&self, key: &str);
     //fn get_block_iter(&self) -> Result<Box<dyn Iterator<Item = Block>>, BlockchainError>;
 
-    fn get_utxo_set(&self) -> HashMap<String, Vec<TxOutput>>;
-    fn write_utxo(&self, txid: &str, outs: Vec<TxOutput>) -> Result<(), BlockchainError>;
+    fn get_utxo_set(&self) -> HashMap<String, Vec<UtxoEntry>>;
+    fn get_utxo(&self, txid: &str) -> Result<Option<Vec<UtxoEntry>>, BlockchainError>;
+    fn write_utxo(&self, txid: &str, outs: Vec<UtxoEntry>) -> Result<(), BlockchainError>;
+    fn delete_utxo(&self, txid: &str);
     fn clear_utxo_set(&self);
 }
 
@@ -0,0 +1,97 @@
+use std::{fmt, str::FromStr};
+use serde::{Serialize, Deserialize};
+
+use crate::error::BlockchainError;
+
+// 一枚代币包含的最小单位数量
+pub const COIN: u64 = 100_000_000;
+// 代币金额的小数位数
+pub const COIN_DECIMALS: usize = 8;
+
+/*
+ * 代币金额，以最小单位计数
+ *     金额不能为负数，加减乘运算都使用checked版本，溢出时返回None，由调用方决定
+ * 交易无效还是报错。字符串按十进制代币数量解析和格式化，例如"1.5"表示150000000个最小单位。
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+    pub const MAX: Amount = Amount(u64::MAX);
+
+    // 以最小单位表示的金额
+    pub const fn from_sat(sat: u64) -> Self {
+        Self(sat)
+    }
+
+    // 整数枚代币，超出范围时取最大值
+    pub const fn from_coins(coins: u64) -> Self {
+        Self(coins.saturating_mul(COIN))
+    }
+
+    pub fn as_sat(&self) -> u64 {
+        self.0
+    }
+
+    pub fn checked_add(self, rhs: Amount) -> Option<Amount> {
+        self.0.checked_add(rhs.0).map(Amount)
+    }
+
+    pub fn checked_sub(self, rhs: Amount) -> Option<Amount> {
+        self.0.checked_sub(rhs.0).map(Amount)
+    }
+
+    pub fn checked_mul(self, rhs: u64) -> Option<Amount> {
+        self.0.checked_mul(rhs).map(Amount)
+    }
+
+    // 余额统计等只用于展示的场合，溢出时取最大值
+    pub fn saturating_add(self, rhs: Amount) -> Amount {
+        Amount(self.0.saturating_add(rhs.0))
+    }
+
+    // 所有金额之和，溢出时返回None
+    pub fn checked_sum<I>(amounts: I) -> Option<Amount>
+    where
+        I: IntoIterator<Item = Amount>
+    {
+        amounts.into_iter().try_fold(Amount::ZERO, Amount::checked_add)
+    }
+}
+
+impl fmt::Display for Amount {
+    // 格式化为十进制的代币数量，去掉小数部分末尾的0
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (coins, sat) = (self.0 / COIN, self.0 % COIN);
+        if sat == 0 {
+            return write!(f, "{}", coins);
+        }
+        let fraction = format!("{:0width$}", sat, width = COIN_DECIMALS);
+        write!(f, "{}.{}", coins, fraction.trim_end_matches('0'))
+    }
+}
+
+impl FromStr for Amount {
+    type Err = BlockchainError;
+
+    // 解析十进制的代币数量，最多COIN_DECIMALS位小数，负数和超出范围的金额无效
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BlockchainError::InvalidAmount(s.to_string());
+        let (coins, fraction) = s.split_once('.').unwrap_or((s, ""));
+        if coins.is_empty() && fraction.is_empty() || fraction.len() > COIN_DECIMALS {
+            return Err(invalid());
+        }
+        if !coins.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        let coins = if coins.is_empty() { 0 } else { coins.parse::<u64>().map_err(|_| invalid())? };
+        let fraction = format!("{:0<width$}", fraction, width = COIN_DECIMALS);
+        let sat = fraction.parse::<u64>().map_err(|_| invalid())?;
+        Amount::from_sat(coins)
+            .checked_mul(COIN)
+            .and_then(|amount| amount.checked_add(Amount::from_sat(sat)))
+            .ok_or_else(invalid)
+    }
+}
@@ -2,8 +2,12 @@ mod transaction;
 mod tx_input;
 mod tx_output;
 mod utxo_set;
+mod script;
+mod amount;
 
 pub use transaction::*;
-pub use tx_input::TxInput;
+pub use tx_input::*;
 pub use tx_output::TxOutput;
-pub use utxo_set::UTXOSet;
+pub use script::*;
+pub use amount::*;
+pub use utxo_set::{UTXOSet, UtxoEntry, COINBASE_MATURITY};
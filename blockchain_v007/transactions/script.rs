@@ -0,0 +1,541 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    Transaction, ChainParams, LOCKTIME_THRESHOLD, SEQUENCE_FINAL, SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_TYPE_FLAG, SEQUENCE_LOCKTIME_MASK,
+    hash_pub_key, error::BlockchainError, utils::{serialize, deserialize, sha256_digest, ecdsa_p256_sha256_sign_verify},
+};
+
+// 一个脚本最多包含的操作数量
+pub const MAX_SCRIPT_OPS: usize = 201;
+// 一次压栈的数据最大字节数
+pub const MAX_PUSH_SIZE: usize = 520;
+// 执行过程中栈的最大深度
+pub const MAX_STACK_SIZE: usize = 1000;
+// 多重签名脚本中最多包含的公钥数量
+pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+
+/* 脚本操作码 */
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum Op {
+    PushData(Vec<u8>),  // 将数据压入栈顶
+    Dup,                // 复制栈顶元素
+    Drop,               // 弹出栈顶元素
+    Hash160,            // 栈顶元素替换为其SHA256+RIPEMD160哈希
+    Sha256,             // 栈顶元素替换为其SHA256哈希
+    Equal,              // 弹出两个元素，相等压入1，否则压入空
+    EqualVerify,        // 同Equal，不相等则脚本失败
+    Verify,             // 弹出栈顶元素，为假则脚本失败
+    CheckSig,           // 弹出公钥和签名，验证签名，成功压入1，否则压入空
+    CheckSigVerify,     // 同CheckSig，验证失败则脚本失败
+    CheckMultiSig,      // 弹出n、n个公钥、m和m个签名，签名按公钥顺序全部验证通过压入1，否则压入空
+    CheckMultiSigVerify,    // 同CheckMultiSig，验证失败则脚本失败
+    If,                 // 弹出栈顶元素，为真则执行到Else或EndIf之间的操作
+    Else,               // 执行If分支中没有执行的部分
+    EndIf,              // 结束条件分支
+    CheckLockTimeVerify,    // 栈顶元素为绝对时间锁，交易的lock_time没有达到该值则脚本失败，不弹出栈顶元素
+    CheckSequenceVerify,    // 栈顶元素为相对时间锁，当前输入的序列号没有达到该值则脚本失败，不弹出栈顶元素
+}
+
+/*
+ * 基于栈的脚本
+ *     每个交易输出带有一个锁定脚本script_pubkey，花费该输出的交易输入带有一个解锁脚本script_sig。
+ * 验证时先执行script_sig，再在同一个栈上执行script_pubkey，最终栈顶为真则解锁成功。
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct Script {
+    ops: Vec<Op>,
+}
+
+impl Script {
+    pub fn new(ops: Vec<Op>) -> Self {
+        Self { ops }
+    }
+
+    /*
+     * 支付给公钥hash（P2PKH）的锁定脚本：
+     * OP_DUP OP_HASH160 <pub_key_hash> OP_EQUALVERIFY OP_CHECKSIG
+     */
+    pub fn new_p2pkh(pub_key_hash: &[u8]) -> Self {
+        Self::new(vec![
+            Op::Dup,
+            Op::Hash160,
+            Op::PushData(pub_key_hash.to_vec()),
+            Op::EqualVerify,
+            Op::CheckSig,
+        ])
+    }
+
+    // P2PKH的解锁脚本：<signature> <pub_key>
+    pub fn new_p2pkh_sig(signature: Vec<u8>, pub_key: &[u8]) -> Self {
+        Self::new(vec![Op::PushData(signature), Op::PushData(pub_key.to_vec())])
+    }
+
+    /*
+     * m-of-n多重签名脚本，需要pub_keys中任意required个公钥对应的签名：
+     * <m> <pub_key_1> ... <pub_key_n> <n> OP_CHECKMULTISIG
+     */
+    pub fn new_multisig(required: usize, pub_keys: &[Vec<u8>]) -> Self {
+        let mut ops = vec![Op::PushData(num_to_data(required as u64))];
+        ops.extend(pub_keys.iter().map(|pub_key| Op::PushData(pub_key.clone())));
+        ops.push(Op::PushData(num_to_data(pub_keys.len() as u64)));
+        ops.push(Op::CheckMultiSig);
+        Self::new(ops)
+    }
+
+    /*
+     * 支付给脚本hash（P2SH）的锁定脚本，花费时需要提供hash对应的赎回脚本：
+     * OP_HASH160 <script_hash> OP_EQUAL
+     */
+    pub fn new_p2sh(script_hash: &[u8]) -> Self {
+        Self::new(vec![
+            Op::Hash160,
+            Op::PushData(script_hash.to_vec()),
+            Op::Equal,
+        ])
+    }
+
+    // P2SH的解锁脚本：<signature_1> ... <signature_m> <redeem_script>
+    pub fn new_p2sh_sig(signatures: Vec<Vec<u8>>, redeem_script: &Script) -> Result<Self, BlockchainError> {
+        let mut ops: Vec<Op> = signatures.into_iter().map(Op::PushData).collect();
+        ops.push(Op::PushData(serialize(redeem_script)?));
+        Ok(Self::new(ops))
+    }
+
+    // 哈希时间锁合约的锁定脚本，见Htlc
+    pub fn new_htlc(htlc: &Htlc) -> Self {
+        Self::new(vec![
+            Op::If,
+            Op::Sha256,
+            Op::PushData(htlc.hash_lock.clone()),
+            Op::EqualVerify,
+            Op::Dup,
+            Op::Hash160,
+            Op::PushData(htlc.receiver.clone()),
+            Op::Else,
+            Op::PushData(num_to_data(htlc.timeout as u64)),
+            Op::CheckLockTimeVerify,
+            Op::Drop,
+            Op::Dup,
+            Op::Hash160,
+            Op::PushData(htlc.refund.clone()),
+            Op::EndIf,
+            Op::EqualVerify,
+            Op::CheckSig,
+        ])
+    }
+
+    // 哈希时间锁合约接收方的解锁脚本：<signature> <pub_key> <preimage> 1
+    pub fn new_htlc_claim_sig(signature: Vec<u8>, pub_key: &[u8], preimage: &[u8]) -> Self {
+        Self::new(vec![
+            Op::PushData(signature),
+            Op::PushData(pub_key.to_vec()),
+            Op::PushData(preimage.to_vec()),
+            Op::PushData(bool_to_data(true)),
+        ])
+    }
+
+    // 哈希时间锁合约发送方退款的解锁脚本：<signature> <pub_key> 0
+    pub fn new_htlc_refund_sig(signature: Vec<u8>, pub_key: &[u8]) -> Self {
+        Self::new(vec![
+            Op::PushData(signature),
+            Op::PushData(pub_key.to_vec()),
+            Op::PushData(bool_to_data(false)),
+        ])
+    }
+
+    // 脚本的hash，即P2SH锁定脚本和地址中使用的脚本hash
+    pub fn get_hash(&self) -> Vec<u8> {
+        hash_pub_key(&serialize(self).unwrap_or_default())
+    }
+
+    // 如果是P2PKH锁定脚本，返回其中的公钥hash
+    pub fn get_pub_key_hash(&self) -> Option<&[u8]> {
+        match self.ops.as_slice() {
+            [Op::Dup, Op::Hash160, Op::PushData(pub_key_hash), Op::EqualVerify, Op::CheckSig] => Some(pub_key_hash),
+            _ => None,
+        }
+    }
+
+    // 如果是P2SH锁定脚本，返回其中的脚本hash
+    pub fn get_script_hash(&self) -> Option<&[u8]> {
+        match self.ops.as_slice() {
+            [Op::Hash160, Op::PushData(script_hash), Op::Equal] => Some(script_hash),
+            _ => None,
+        }
+    }
+
+    // 如果是哈希时间锁合约的锁定脚本，返回合约的内容
+    pub fn get_htlc(&self) -> Option<Htlc> {
+        match self.ops.as_slice() {
+            [Op::If, Op::Sha256, Op::PushData(hash_lock), Op::EqualVerify, Op::Dup, Op::Hash160, Op::PushData(receiver),
+             Op::Else, Op::PushData(timeout), Op::CheckLockTimeVerify, Op::Drop, Op::Dup, Op::Hash160, Op::PushData(refund),
+             Op::EndIf, Op::EqualVerify, Op::CheckSig] => Some(Htlc {
+                hash_lock: hash_lock.clone(),
+                receiver: receiver.clone(),
+                refund: refund.clone(),
+                timeout: u32::try_from(data_to_num(timeout)?).ok()?,
+            }),
+            _ => None,
+        }
+    }
+
+    // 解锁脚本中压栈的数据里，SHA256哈希等于hash_lock的原像
+    pub fn find_preimage(&self, hash_lock: &[u8]) -> Option<&[u8]> {
+        self.ops.iter().find_map(|op| match op {
+            Op::PushData(data) if sha256_digest(data) == hash_lock => Some(data.as_slice()),
+            _ => None,
+        })
+    }
+
+    // 如果是多重签名脚本，返回需要的签名数量m和全部公钥
+    pub fn get_multisig(&self) -> Option<(usize, Vec<&[u8]>)> {
+        let (last, ops) = self.ops.split_last()?;
+        if *last != Op::CheckMultiSig || ops.len() < 2 {
+            return None;
+        }
+        let required = match &ops[0] {
+            Op::PushData(data) => data_to_num(data)? as usize,
+            _ => return None,
+        };
+        let count = match &ops[ops.len() - 1] {
+            Op::PushData(data) => data_to_num(data)? as usize,
+            _ => return None,
+        };
+        let pub_keys = ops[1..ops.len() - 1].iter()
+            .map(|op| match op {
+                Op::PushData(pub_key) => Some(pub_key.as_slice()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        if pub_keys.len() != count || required == 0 || required > count {
+            return None;
+        }
+        Some((required, pub_keys))
+    }
+
+    // 脚本是否只包含压栈操作，解锁脚本必须满足该条件
+    pub fn is_push_only(&self) -> bool {
+        self.ops.iter().all(|op| matches!(op, Op::PushData(_)))
+    }
+
+    /*
+     * 脚本中的签名验证操作数量
+     *     多重签名操作按前一个压栈的公钥数量n计算，无法确定时按MAX_PUBKEYS_PER_MULTISIG计算；
+     * 解锁脚本最后压栈的数据如果是P2SH的赎回脚本，计入赎回脚本中的签名验证操作。
+     */
+    pub fn sigop_count(&self) -> usize {
+        let mut count = 0;
+        for (idx, op) in self.ops.iter().enumerate() {
+            match op {
+                Op::CheckSig | Op::CheckSigVerify => count += 1,
+                Op::CheckMultiSig | Op::CheckMultiSigVerify => {
+                    count += match idx.checked_sub(1).map(|prev| &self.ops[prev]) {
+                        Some(Op::PushData(data)) => data_to_num(data).map_or(MAX_PUBKEYS_PER_MULTISIG, |count| count as usize),
+                        _ => MAX_PUBKEYS_PER_MULTISIG,
+                    };
+                },
+                _ => {},
+            }
+        }
+        if let (true, Some(Op::PushData(data))) = (self.is_push_only(), self.ops.last()) {
+            if let Ok(redeem_script) = deserialize::<Script>(data) {
+                count += redeem_script.sigop_count();
+            }
+        }
+        count
+    }
+
+    pub fn get_ops(&self) -> &[Op] {
+        self.ops.as_slice()
+    }
+
+    /*
+     * 验证解锁脚本能否解锁锁定脚本：
+     * 1. 解锁脚本只能包含压栈操作
+     * 2. 依次执行解锁脚本和锁定脚本，执行过程中不能出错
+     * 3. 执行结束后栈顶元素必须为真
+     * 4. 如果锁定脚本是P2SH，解锁脚本最后压栈的数据是赎回脚本，在解锁脚本执行之后的栈上
+     *    （去掉赎回脚本本身）再执行赎回脚本，栈顶元素同样必须为真
+     */
+    pub fn verify(script_sig: &Script, script_pubkey: &Script, checker: &TransactionChecker) -> Result<(), BlockchainError> {
+        if !script_sig.is_push_only() {
+            return Err(BlockchainError::ScriptFailed("script_sig is not push only"));
+        }
+
+        let mut stack = vec![];
+        script_sig.eval(&mut stack, checker)?;
+        let mut redeem_stack = stack.clone();
+        script_pubkey.eval(&mut stack, checker)?;
+        check_top(&stack)?;
+
+        if script_pubkey.get_script_hash().is_some() {
+            let redeem_script: Script = deserialize(&pop(&mut redeem_stack)?)
+                .map_err(|_| BlockchainError::ScriptFailed("invalid redeem script"))?;
+            redeem_script.eval(&mut redeem_stack, checker)?;
+            check_top(&redeem_stack)?;
+        }
+        Ok(())
+    }
+
+    /*
+     * 在stack上执行脚本
+     *     conditions记录嵌套的If/Else分支是否执行，只有所有分支都为真时才执行普通操作
+     */
+    fn eval(&self, stack: &mut Vec<Vec<u8>>, checker: &TransactionChecker) -> Result<(), BlockchainError> {
+        if self.ops.len() > MAX_SCRIPT_OPS {
+            return Err(BlockchainError::ScriptFailed("too many operations"));
+        }
+
+        let mut conditions: Vec<bool> = vec![];
+        for op in &self.ops {
+            let executing = conditions.iter().all(|condition| *condition);
+            match op {
+                Op::If => {
+                    let condition = executing && cast_to_bool(&pop(stack)?);
+                    conditions.push(condition);
+                    continue;
+                },
+                Op::Else => {
+                    let condition = conditions.last_mut().ok_or(BlockchainError::ScriptFailed("OP_ELSE without OP_IF"))?;
+                    *condition = !*condition;
+                    continue;
+                },
+                Op::EndIf => {
+                    conditions.pop().ok_or(BlockchainError::ScriptFailed("OP_ENDIF without OP_IF"))?;
+                    continue;
+                },
+                _ if !executing => continue,
+                _ => {},
+            }
+
+            match op {
+                Op::PushData(data) => {
+                    if data.len() > MAX_PUSH_SIZE {
+                        return Err(BlockchainError::ScriptFailed("push data is too large"));
+                    }
+                    stack.push(data.clone());
+                },
+                Op::Dup => {
+                    let top = stack.last().cloned().ok_or(BlockchainError::ScriptFailed("stack underflow"))?;
+                    stack.push(top);
+                },
+                Op::Drop => {
+                    pop(stack)?;
+                },
+                Op::Hash160 => {
+                    let data = pop(stack)?;
+                    stack.push(hash_pub_key(&data));
+                },
+                Op::Sha256 => {
+                    let data = pop(stack)?;
+                    stack.push(sha256_digest(&data));
+                },
+                Op::Equal | Op::EqualVerify => {
+                    let (a, b) = (pop(stack)?, pop(stack)?);
+                    if *op == Op::EqualVerify {
+                        if a != b {
+                            return Err(BlockchainError::ScriptFailed("OP_EQUALVERIFY failed"));
+                        }
+                    } else {
+                        stack.push(bool_to_data(a == b));
+                    }
+                },
+                Op::Verify => {
+                    if !cast_to_bool(&pop(stack)?) {
+                        return Err(BlockchainError::ScriptFailed("OP_VERIFY failed"));
+                    }
+                },
+                Op::CheckSig | Op::CheckSigVerify => {
+                    let pub_key = pop(stack)?;
+                    let signature = pop(stack)?;
+                    let valid = checker.check_sig(&signature, &pub_key);
+                    if *op == Op::CheckSigVerify {
+                        if !valid {
+                            return Err(BlockchainError::InvalidSignature(checker.tx.get_id()));
+                        }
+                    } else {
+                        stack.push(bool_to_data(valid));
+                    }
+                },
+                Op::CheckMultiSig | Op::CheckMultiSigVerify => {
+                    let count = pop_num(stack)?;
+                    if count > MAX_PUBKEYS_PER_MULTISIG {
+                        return Err(BlockchainError::ScriptFailed("too many public keys"));
+                    }
+                    let pub_keys = (0..count).map(|_| pop(stack)).collect::<Result<Vec<_>, _>>()?;
+                    let required = pop_num(stack)?;
+                    if required > count {
+                        return Err(BlockchainError::ScriptFailed("too many signatures"));
+                    }
+                    let signatures = (0..required).map(|_| pop(stack)).collect::<Result<Vec<_>, _>>()?;
+
+                    // 公钥和签名出栈的顺序与压栈相反，签名必须按照公钥的顺序排列，每个公钥最多匹配一个签名
+                    let mut pub_keys = pub_keys.iter();
+                    let valid = signatures.iter().all(|signature| {
+                        pub_keys.any(|pub_key| checker.check_sig(signature, pub_key))
+                    });
+                    if *op == Op::CheckMultiSigVerify {
+                        if !valid {
+                            return Err(BlockchainError::InvalidSignature(checker.tx.get_id()));
+                        }
+                    } else {
+                        stack.push(bool_to_data(valid));
+                    }
+                },
+                Op::CheckLockTimeVerify => {
+                    let lock_time = stack.last().and_then(|top| data_to_num(top))
+                        .ok_or(BlockchainError::ScriptFailed("invalid lock time"))?;
+                    if !checker.check_lock_time(lock_time) {
+                        return Err(BlockchainError::ScriptFailed("OP_CHECKLOCKTIMEVERIFY failed"));
+                    }
+                },
+                Op::CheckSequenceVerify => {
+                    let sequence = stack.last().and_then(|top| data_to_num(top))
+                        .ok_or(BlockchainError::ScriptFailed("invalid sequence"))?;
+                    if !checker.check_sequence(sequence) {
+                        return Err(BlockchainError::ScriptFailed("OP_CHECKSEQUENCEVERIFY failed"));
+                    }
+                },
+                Op::If | Op::Else | Op::EndIf => unreachable!(),
+            }
+            if stack.len() > MAX_STACK_SIZE {
+                return Err(BlockchainError::ScriptFailed("stack overflow"));
+            }
+        }
+        if !conditions.is_empty() {
+            return Err(BlockchainError::ScriptFailed("unbalanced conditional"));
+        }
+        Ok(())
+    }
+}
+
+/*
+ * 脚本执行时对交易的检查
+ *     签名的内容是交易的签名hash：清空所有输入的解锁脚本，并将当前输入的解锁脚本
+ * 替换为被花费输出的锁定脚本，再计算交易的hash。
+ */
+pub struct TransactionChecker<'a> {
+    tx: &'a Transaction,            // 正在验证的交易
+    input: usize,                   // 正在验证的输入序号
+    script_pubkey: &'a Script,      // 被花费输出的锁定脚本
+}
+
+impl<'a> TransactionChecker<'a> {
+    pub fn new(tx: &'a Transaction, input: usize, script_pubkey: &'a Script) -> Self {
+        Self { tx, input, script_pubkey }
+    }
+
+    // 使用公钥验证对签名hash的签名
+    pub fn check_sig(&self, signature: &[u8], pub_key: &[u8]) -> bool {
+        let sighash = self.tx.signature_hash(self.input, self.script_pubkey);
+        ecdsa_p256_sha256_sign_verify(pub_key, signature, sighash.as_bytes())
+    }
+
+    /*
+     * 检查交易的绝对时间锁是否达到脚本要求的lock_time：
+     * 两者必须同为区块高度或同为时间戳，交易的lock_time不小于lock_time，
+     * 并且当前输入的序列号不是SEQUENCE_FINAL，否则交易的lock_time不生效
+     */
+    pub fn check_lock_time(&self, lock_time: u64) -> bool {
+        let tx_lock_time = self.tx.get_lock_time() as u64;
+        let threshold = LOCKTIME_THRESHOLD as u64;
+        (tx_lock_time < threshold) == (lock_time < threshold)
+            && lock_time <= tx_lock_time
+            && self.tx.get_vin()[self.input].get_sequence() != SEQUENCE_FINAL
+    }
+
+    /*
+     * 检查当前输入的相对时间锁是否达到脚本要求的sequence：
+     * 脚本要求的sequence设置了禁用位时不做检查；否则输入的序列号必须启用相对时间锁，
+     * 两者同为区块数量或同为时间，且输入的时间锁不小于要求的值
+     */
+    pub fn check_sequence(&self, sequence: u64) -> bool {
+        let sequence = match u32::try_from(sequence) {
+            Ok(sequence) => sequence,
+            Err(_) => return false,
+        };
+        if sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            return true;
+        }
+        let tx_sequence = self.tx.get_vin()[self.input].get_sequence();
+        if tx_sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            return false;
+        }
+        (tx_sequence & SEQUENCE_LOCKTIME_TYPE_FLAG) == (sequence & SEQUENCE_LOCKTIME_TYPE_FLAG)
+            && (sequence & SEQUENCE_LOCKTIME_MASK) <= (tx_sequence & SEQUENCE_LOCKTIME_MASK)
+    }
+}
+
+/*
+ * 哈希时间锁合约（HTLC）
+ *     接收方提供SHA256哈希等于hash_lock的原像，并用receiver对应的私钥签名即可领取；
+ * 超过timeout之后，发送方可以用refund对应的私钥签名取回。两条网络上使用相同hash_lock的
+ * 合约可以实现原子交换：领取一方的合约时公开了原像，另一方即可用原像领取另一条网络上的合约。
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Htlc {
+    pub hash_lock: Vec<u8>,     // 原像的SHA256哈希
+    pub receiver: Vec<u8>,      // 接收方的公钥hash
+    pub refund: Vec<u8>,        // 退款方的公钥hash
+    pub timeout: u32,           // 退款的绝对时间锁，小于LOCKTIME_THRESHOLD时为区块高度，否则为时间戳
+}
+
+impl Htlc {
+    // 根据接收方和退款方的地址创建合约，两个地址都必须是当前网络的公钥地址
+    pub fn new(hash_lock: Vec<u8>, receiver: &str, refund: &str, timeout: u32, params: &ChainParams) -> Result<Self, BlockchainError> {
+        let pub_key_hash = |address: &str| {
+            params.address_to_script(address)?
+                .get_pub_key_hash()
+                .map(|pub_key_hash| pub_key_hash.to_vec())
+                .ok_or_else(|| BlockchainError::InvalidAddress(address.to_string()))
+        };
+        Ok(Self {
+            hash_lock,
+            receiver: pub_key_hash(receiver)?,
+            refund: pub_key_hash(refund)?,
+            timeout,
+        })
+    }
+}
+
+fn pop(stack: &mut Vec<Vec<u8>>) -> Result<Vec<u8>, BlockchainError> {
+    stack.pop().ok_or(BlockchainError::ScriptFailed("stack underflow"))
+}
+
+fn pop_num(stack: &mut Vec<Vec<u8>>) -> Result<usize, BlockchainError> {
+    data_to_num(&pop(stack)?)
+        .and_then(|value| usize::try_from(value).ok())
+        .ok_or(BlockchainError::ScriptFailed("invalid number"))
+}
+
+fn check_top(stack: &[Vec<u8>]) -> Result<(), BlockchainError> {
+    match stack.last() {
+        Some(top) if cast_to_bool(top) => Ok(()),
+        _ => Err(BlockchainError::ScriptFailed("script evaluated to false")),
+    }
+}
+
+// 栈元素为空或者全为0时为假
+fn cast_to_bool(data: &[u8]) -> bool {
+    data.iter().any(|byte| *byte != 0)
+}
+
+fn bool_to_data(value: bool) -> Vec<u8> {
+    if value { vec![1] } else { vec![] }
+}
+
+// 脚本中的非负整数使用最短的小端字节序表示，0为空
+fn num_to_data(value: u64) -> Vec<u8> {
+    let mut data = value.to_le_bytes().to_vec();
+    while data.last() == Some(&0) {
+        data.pop();
+    }
+    data
+}
+
+fn data_to_num(data: &[u8]) -> Option<u64> {
+    if data.len() > 8 || data.last() == Some(&0) {
+        return None;
+    }
+    Some(data.iter().rev().fold(0, |value, byte| (value << 8) | *byte as u64))
+}
@@ -1,10 +1,49 @@
 use serde::{Serialize, Deserialize};
 
-use crate::{TxInput, TxOutput, utils::{serialize, hash_to_str, ecdsa_p256_sha256_sign_digest, ecdsa_p256_sha256_sign_verify}, UTXOSet, KVStorage, Wallets, hash_pub_key, Blockchain};
+use crate::{Amount, TxInput, TxOutput, SEQUENCE_FINAL, Op, Script, Htlc, TransactionChecker, utils::{serialize, hash_to_str, sha256_digest, ecdsa_p256_sha256_sign_digest}, UTXOSet, KVStorage, Wallets, address_to_pub_key_hash, encode_address, Blockchain, error::BlockchainError};
 
+// coinbase输入中矿工自定义数据的最大字节数
+pub const MAX_COINBASE_DATA: usize = 100;
+// lock_time小于该值时表示区块高度，否则表示Unix时间戳
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
 
-// 挖矿的奖励，20枚代币
-const SUBSIDY: i32 = 20;
+/*
+ * 新交易使用的时间锁
+ *     lock_time: 交易最早可以被打包的区块高度或时间，为0时不限制
+ *     sequence: 所有输入使用的序列号，可以包含相对时间锁
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct TimeLock {
+    pub lock_time: u32,
+    pub sequence: u32,
+}
+
+impl Default for TimeLock {
+    fn default() -> Self {
+        Self {
+            lock_time: 0,
+            sequence: SEQUENCE_FINAL,
+        }
+    }
+}
+
+impl TimeLock {
+    // 绝对时间锁，输入的序列号必须不是SEQUENCE_FINAL，lock_time才会生效
+    pub fn new_absolute(lock_time: u32) -> Self {
+        Self {
+            lock_time,
+            sequence: SEQUENCE_FINAL - 1,
+        }
+    }
+
+    // 相对时间锁，sequence由TxInput::sequence_from_blocks或sequence_from_seconds生成
+    pub fn new_relative(sequence: u32) -> Self {
+        Self {
+            lock_time: 0,
+            sequence,
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 /*
@@ -17,66 +56,166 @@ pub struct Transaction {
     id: String,             // 该笔交易的id
     vin: Vec<TxInput>,      // 交易输入集合，即交易发起方所有可花费账户
     vout: Vec<TxOutput>,    // 交易输出集合，即因交易产生的新的可花费账户
+    lock_time: u32,         // 交易最早可以被打包的区块高度或时间戳，为0时不限制
 }
 
 impl Transaction {
-    // 新区块的奖励，没有输入地址且只有一个输出，to是矿工地址
-    pub fn new_coinbase(to: &str) -> Self {
-        let tx_in = TxInput::default();
-        let tx_out = TxOutput::new(SUBSIDY, to);
+    /*
+     * 新区块的奖励，没有输入地址且只有一个输出，to是矿工地址
+     * reward: 该高度的挖矿奖励与区块中所有交易的手续费之和
+     * height: 区块高度，写入coinbase的输入中，保证不同区块的coinbase交易id不同
+     * data: 矿工自定义的数据，例如extra nonce，最多MAX_COINBASE_DATA字节
+     */
+    pub fn new_coinbase(to: &str, reward: Amount, height: usize, data: &[u8]) -> Self {
+        let mut coinbase_data = (height as u64).to_le_bytes().to_vec();
+        coinbase_data.extend_from_slice(data);
+        let tx_in = TxInput::new_coinbase(coinbase_data);
+        let tx_out = TxOutput::new(reward, to);
         
         let mut tx = Transaction {
             id: String::new(),
             vin: vec![tx_in],
             vout: vec![tx_out],
+            lock_time: 0,
         };
          
         tx.set_hash();
         tx
     }
 
-    // 新的UTXO转账，从from地址转账给to地址，共amount枚代币
-    // 必须检查from地址的代币没有被消费过
-    pub fn new_utxo_transaction<T: KVStorage>(from: &str, to: &str, amount: i32, utxo_set: &UTXOSet<T>, bc: &Blockchain<T>) -> Self {
-        let wallets = Wallets::new().unwrap();
-        let wallet = wallets.get_wallet(from).unwrap();
-        let public_key_hash = hash_pub_key(wallet.get_public_key());
+    /*
+     * 新的UTXO转账，从from地址转账给to地址，共amount枚代币，另外支付fee枚代币作为手续费
+     * 必须检查from地址的代币没有被消费过
+     * from可以是本地钱包的地址，也可以是本地保存了赎回脚本的多重签名地址。多重签名地址的交易
+     * 只包含本地钱包的签名，签名不足时需要交给其他参与者继续签名
+     */
+    pub fn new_utxo_transaction<T: KVStorage>(from: &str, to: &str, amount: Amount, fee: Amount, utxo_set: &UTXOSet<T>, bc: &Blockchain<T>) -> Result<Self, BlockchainError> {
+        Self::new_utxo_transaction_with_lock(from, to, amount, fee, TimeLock::default(), utxo_set, bc)
+    }
+
+    // 带时间锁的UTXO转账，例如到期才能打包的归属支付，或者托管到期后的退款
+    pub fn new_utxo_transaction_with_lock<T: KVStorage>(from: &str, to: &str, amount: Amount, fee: Amount, lock: TimeLock, utxo_set: &UTXOSet<T>, bc: &Blockchain<T>) -> Result<Self, BlockchainError> {
+        let to_script = bc.get_params().address_to_script(to)?;
+        Self::new_payment(from, to_script, amount, fee, lock, utxo_set, bc)
+    }
+
+    // 从from地址向哈希时间锁合约转账amount枚代币，合约输出是交易的第0个输出
+    pub fn new_htlc_transaction<T: KVStorage>(from: &str, htlc: &Htlc, amount: Amount, fee: Amount, utxo_set: &UTXOSet<T>, bc: &Blockchain<T>) -> Result<Self, BlockchainError> {
+        Self::new_payment(from, Script::new_htlc(htlc), amount, fee, TimeLock::default(), utxo_set, bc)
+    }
+
+    // 接收方公开原像，领取txid交易第vout个输出中的哈希时间锁合约，扣除fee之后转给to地址
+    pub fn new_htlc_claim<T: KVStorage>(txid: &str, vout: usize, preimage: &[u8], to: &str, fee: Amount, bc: &Blockchain<T>) -> Result<Self, BlockchainError> {
+        Self::spend_htlc(txid, vout, Some(preimage), to, fee, bc)
+    }
+
+    // 合约超时之后，发送方取回txid交易第vout个输出中的哈希时间锁合约，扣除fee之后转给to地址
+    pub fn new_htlc_refund<T: KVStorage>(txid: &str, vout: usize, to: &str, fee: Amount, bc: &Blockchain<T>) -> Result<Self, BlockchainError> {
+        Self::spend_htlc(txid, vout, None, to, fee, bc)
+    }
+
+    /*
+     * 花费哈希时间锁合约：preimage不为None时按接收方领取，否则按发送方退款
+     * 退款交易的lock_time设置为合约的timeout，在此之前无法打包
+     */
+    fn spend_htlc<T: KVStorage>(txid: &str, vout: usize, preimage: Option<&[u8]>, to: &str, fee: Amount, bc: &Blockchain<T>) -> Result<Self, BlockchainError> {
+        let params = bc.get_params();
+        let to_script = params.address_to_script(to)?;
+        let input = TxInput::new(txid.to_string(), vout);
+        let prev_out = Self::find_prev_output(bc, &input)?;
+        let htlc = prev_out.get_script_pubkey().get_htlc()
+            .ok_or_else(|| BlockchainError::InvalidHtlc(txid.to_string(), vout))?;
+        if let Some(preimage) = preimage {
+            if sha256_digest(preimage) != htlc.hash_lock {
+                return Err(BlockchainError::InvalidHtlc(txid.to_string(), vout));
+            }
+        }
+        let value = prev_out.get_value();
+        let output_value = value.checked_sub(fee)
+            .filter(|output_value| *output_value > Amount::ZERO)
+            .ok_or(BlockchainError::InsufficientFunds(fee, value))?;
+
+        let pub_key_hash = if preimage.is_some() { &htlc.receiver } else { &htlc.refund };
+        let wallets = Wallets::new()?;
+        let wallet = wallets.get_wallet_by_pub_key_hash(pub_key_hash)
+            .ok_or_else(|| BlockchainError::UnknownWallet(encode_address(params.address_version, pub_key_hash)))?;
+        let lock = match preimage {
+            Some(_) => TimeLock::default(),
+            None => TimeLock::new_absolute(htlc.timeout),
+        };
+
+        let mut tx = Transaction {
+            id: String::new(),
+            vin: vec![TxInput::with_sequence(txid.to_string(), vout, lock.sequence)],
+            vout: vec![TxOutput::with_script(output_value, to_script)],
+            lock_time: lock.lock_time,
+        };
+        tx.set_hash();
+
+        let sighash = tx.signature_hash(0, prev_out.get_script_pubkey());
+        let signature = ecdsa_p256_sha256_sign_digest(wallet.get_private_key(), sighash.as_bytes());
+        let script_sig = match preimage {
+            Some(preimage) => Script::new_htlc_claim_sig(signature, wallet.get_public_key(), preimage),
+            None => Script::new_htlc_refund_sig(signature, wallet.get_public_key()),
+        };
+        tx.vin[0].set_script_sig(script_sig);
+        Ok(tx)
+    }
+
+    /*
+     * 从from地址支付amount枚代币给to_script锁定的输出，另外支付fee枚代币作为手续费
+     * from可以是本地钱包的地址，也可以是本地保存了赎回脚本的多重签名地址
+     */
+    fn new_payment<T: KVStorage>(from: &str, to_script: Script, amount: Amount, fee: Amount, lock: TimeLock, utxo_set: &UTXOSet<T>, bc: &Blockchain<T>) -> Result<Self, BlockchainError> {
+        let from_script = bc.get_params().address_to_script(from)?;
+        let wallets = Wallets::new()?;
+        let public_key_hash = address_to_pub_key_hash(from);
+        let is_local = match from_script.get_script_hash() {
+            Some(script_hash) => wallets.get_script_by_hash(script_hash).is_some(),
+            None => wallets.get_wallet(from).is_some(),
+        };
+        if !is_local {
+            return Err(BlockchainError::UnknownWallet(from.to_string()));
+        }
 
         // 基于pubkey查询utxo集合中该账户的可花费账户余额总和accumulated
         // valid_outputs是可以花费的所有账户列表。必须基于pubkey在链上搜索，
         // 以便任何第三方都可以验证
-        let (accumulated, valid_outputs) = utxo_set.find_spendable_outputs(&public_key_hash, amount);
-        if accumulated < amount {
-            panic!("Error not enough funds");
+        let total = amount.checked_add(fee)
+            .ok_or_else(|| BlockchainError::InvalidAmount(amount.to_string()))?;
+        let (accumulated, valid_outputs) = utxo_set.find_spendable_outputs(&public_key_hash, total);
+        if accumulated < total {
+            return Err(BlockchainError::InsufficientFunds(total, accumulated));
         }
 
         // 本次交易使用掉的utxo账户，将之前交易产生的TxOutput包装为TxInput
         let mut inputs = vec![];
         for (txid, outputs) in valid_outputs {
             for idx in outputs {
-                let input = TxInput::new(txid.clone(), idx.clone(), wallet.get_public_key().to_vec());
+                let input = TxInput::with_sequence(txid.clone(), idx, lock.sequence);
                 inputs.push(input);
             }
         }
 
         // 本次交易新生成的utxo账户
-        let mut outputs = vec![TxOutput::new(amount, &to)];
-        // 这是对交易发起方的utxo找零账户
-        if accumulated > amount {
-            outputs.push(TxOutput::new(accumulated - amount, &from));
+        let mut outputs = vec![TxOutput::with_script(amount, to_script)];
+        // 这是对交易发起方的utxo找零账户，输入与输出的差额即为手续费
+        if let Some(change) = accumulated.checked_sub(total).filter(|change| *change > Amount::ZERO) {
+            outputs.push(TxOutput::with_script(change, from_script));
         }
 
         let mut tx = Transaction {
             id: String::new(),
             vin: inputs,
             vout: outputs, 
+            lock_time: lock.lock_time,
         };
         // 生成新的交易id
         tx.set_hash();
         // 发起方使用私钥，对交易签名
-        tx.sign(bc, wallet.get_private_key());
+        tx.sign(bc, &wallets)?;
 
-        tx
+        Ok(tx)
     }
 
     // 对交易本身签名
@@ -86,80 +225,153 @@ impl Transaction {
         }
     }
 
-    fn sign<T: KVStorage>(&mut self, bc: &Blockchain<T>, private_key: &[u8]) {
-        let mut tx_copy = self.trimmed_copy();
+    // 包含解锁脚本在内的完整交易的hash，作为区块Merkle树的叶子节点
+    pub fn get_full_hash(&self) -> String {
+        serialize(self).map(|tx_ser| hash_to_str(&tx_ser)).unwrap_or_default()
+    }
+
+    // 校验交易id是否由交易内容计算得出，交易id在签名之前生成，因此需要清空解锁脚本
+    // coinbase输入的解锁脚本中存放的是区块高度，参与交易id的计算
+    pub fn verify_hash(&self) -> bool {
+        let mut tx_copy = self.clone();
+        tx_copy.id = String::new();
+        if !self.is_coinbase() {
+            for vin in tx_copy.vin.iter_mut() {
+                vin.set_script_sig(Script::default());
+            }
+        }
+        tx_copy.set_hash();
+        tx_copy.id == self.id
+    }
+
+    /*
+     * 使用本地钱包对所有输入签名
+     * 1. P2PKH输入：必须是支付给本地钱包的输出，使用该钱包的私钥签名
+     * 2. P2SH多重签名输入：本地必须保存了赎回脚本。保留解锁脚本中已有的有效签名，
+     *    并加上本地钱包的签名，签名按照赎回脚本中公钥的顺序排列，最多需要m个。
+     *    签名不足m个时交易还不能通过验证，可以交给其他参与者继续调用sign
+     */
+    pub fn sign<T: KVStorage>(&mut self, bc: &Blockchain<T>, wallets: &Wallets) -> Result<(), BlockchainError> {
+        for idx in 0..self.vin.len() {
+            // 查找输入引用的交易输出
+            let prev_out = Self::find_prev_output(bc, &self.vin[idx])?;
+            let script_pubkey = prev_out.get_script_pubkey();
+            let sighash = self.signature_hash(idx, script_pubkey);
 
-        for (idx, vin) in self.vin.iter_mut().enumerate() {
-            // 查找输入引用的交易
-            let prev_tx_option = bc.find_transaction(vin.get_txid());
-            if prev_tx_option.is_none() {
-                panic!("ERROR: Previous transaction is not correct")
+            if let Some(pub_key_hash) = script_pubkey.get_pub_key_hash() {
+                let wallet = wallets.get_wallet_by_pub_key_hash(pub_key_hash)
+                    .ok_or_else(|| BlockchainError::InvalidSignature(self.id.clone()))?;
+                // 使用私钥对签名hash签名
+                let signature = ecdsa_p256_sha256_sign_digest(wallet.get_private_key(), sighash.as_bytes());
+                self.vin[idx].set_script_sig(Script::new_p2pkh_sig(signature, wallet.get_public_key()));
+                continue;
             }
-            let prev_tx = prev_tx_option.unwrap();
-            tx_copy.vin[idx].set_signature(vec![]);
-            tx_copy.vin[idx].set_pub_key(prev_tx.vout[vin.get_vout()].get_pub_key_hash());
-            tx_copy.set_hash();
 
-            tx_copy.vin[idx].set_pub_key(&vec![]);
+            let redeem_script = script_pubkey.get_script_hash()
+                .and_then(|script_hash| wallets.get_script_by_hash(script_hash))
+                .cloned()
+                .ok_or_else(|| BlockchainError::InvalidSignature(self.id.clone()))?;
+            let (required, pub_keys) = redeem_script.get_multisig()
+                .ok_or_else(|| BlockchainError::InvalidSignature(self.id.clone()))?;
 
-            // 使用私钥对数据签名
-            let signature = ecdsa_p256_sha256_sign_digest(private_key, tx_copy.id.as_bytes());
-            vin.set_signature(signature);
+            // 解锁脚本中已有的签名，即除最后的赎回脚本之外压栈的数据
+            let existing: Vec<Vec<u8>> = match self.vin[idx].get_script_sig().get_ops().split_last() {
+                Some((_, ops)) => ops.iter()
+                    .filter_map(|op| match op {
+                        Op::PushData(data) => Some(data.clone()),
+                        _ => None,
+                    })
+                    .collect(),
+                None => vec![],
+            };
+            let checker = TransactionChecker::new(self, idx, script_pubkey);
+            let mut signatures = vec![];
+            for pub_key in pub_keys {
+                if signatures.len() == required {
+                    break;
+                }
+                if let Some(signature) = existing.iter().find(|signature| checker.check_sig(signature, pub_key)) {
+                    signatures.push(signature.clone());
+                } else if let Some(wallet) = wallets.get_wallet_by_pub_key(pub_key) {
+                    signatures.push(ecdsa_p256_sha256_sign_digest(wallet.get_private_key(), sighash.as_bytes()));
+                }
+            }
+            self.vin[idx].set_script_sig(Script::new_p2sh_sig(signatures, &redeem_script)?);
         }
+        Ok(())
     }
 
-    pub fn verify<T: KVStorage>(&self, bc: &Blockchain<T>) -> bool {
+    /*
+     * 计算第input个输入的签名hash：清空所有输入的解锁脚本，并将第input个输入的解锁脚本
+     * 替换为被花费输出的锁定脚本，然后计算交易的hash
+     */
+    pub fn signature_hash(&self, input: usize, script_pubkey: &Script) -> String {
+        let mut tx_copy = self.clone();
+        tx_copy.id = String::new();
+        for (idx, vin) in tx_copy.vin.iter_mut().enumerate() {
+            let script_sig = if idx == input { script_pubkey.clone() } else { Script::default() };
+            vin.set_script_sig(script_sig);
+        }
+        tx_copy.set_hash();
+        tx_copy.id
+    }
+
+    // 验证交易的所有输入：输入引用的交易输出必须存在，且解锁脚本能够解锁该输出的锁定脚本
+    pub fn verify<T: KVStorage>(&self, bc: &Blockchain<T>) -> Result<(), BlockchainError> {
         if self.is_coinbase() {
-            return true;
+            return Ok(());
         }
 
-        let mut tx_copy = self.trimmed_copy();
         for (idx, vin) in self.vin.iter().enumerate() {
-            let prev_tx_option = bc.find_transaction(vin.get_txid());
-            if prev_tx_option.is_none() {
-                panic!("ERROR: Previous transaction is not correct")
-            }
-            let prev_tx = prev_tx_option.unwrap();
-            tx_copy.vin[idx].set_signature(vec![]);
-            tx_copy.vin[idx].set_pub_key(prev_tx.vout[vin.get_vout()].get_pub_key_hash());
-            tx_copy.set_hash();
-
-            tx_copy.vin[idx].set_pub_key(&vec![]);
-
-            // 使用公钥验证签名
-            let verify = ecdsa_p256_sha256_sign_verify(
-                vin.get_pub_key(),
-                vin.get_signature(),
-                tx_copy.id.as_bytes(),
-            );
-            if !verify {
-                return false;
-            }
+            let prev_out = Self::find_prev_output(bc, vin)?;
+            let checker = TransactionChecker::new(self, idx, prev_out.get_script_pubkey());
+            Script::verify(vin.get_script_sig(), prev_out.get_script_pubkey(), &checker)?;
+        }
+        Ok(())
+    }
+
+    // 在UTXO集合中查找输入引用的交易输出，输出不存在或者已经被花费时返回MissingPrevTx
+    fn find_prev_output<T: KVStorage>(bc: &Blockchain<T>, vin: &TxInput) -> Result<TxOutput, BlockchainError> {
+        bc.get_utxo(&vin.get_txid(), vin.get_vout())
+            .map(|entry| entry.get_output().clone())
+            .ok_or_else(|| BlockchainError::MissingPrevTx(vin.get_txid()))
+    }
+
+    /*
+     * 交易在高度为height、中位时间为median_time的区块中是否已经生效：
+     * lock_time为0，或者已经达到lock_time，或者所有输入的序列号都是SEQUENCE_FINAL
+     */
+    pub fn is_final(&self, height: usize, median_time: i64) -> bool {
+        if self.lock_time == 0 {
+            return true;
         }
-        true
+        let reached = if self.lock_time < LOCKTIME_THRESHOLD {
+            (self.lock_time as usize) < height
+        } else {
+            (self.lock_time as i64) < median_time
+        };
+        reached || self.vin.iter().all(|vin| vin.get_sequence() == SEQUENCE_FINAL)
     }
 
     // 判断是否是 coinbase 交易
     pub fn is_coinbase(&self) -> bool {
-        self.vin.len() == 1 && self.vin[0].get_pub_key().len() == 0
+        self.vin.len() == 1 && self.vin[0].get_txid().is_empty()
     }
 
-    // 清空TxInput的pub_key，复制transaction
-    fn trimmed_copy(&self) -> Transaction {
-        let mut inputs = vec![];
-        let mut outputs = vec![];
-        for input in &self.vin {
-            let txinput = TxInput::new(input.get_txid(), input.get_vout(), vec![]);
-            inputs.push(txinput);
-        }
-        for output in &self.vout {
-            outputs.push(output.clone());
+    // coinbase交易中写入的区块高度，数据格式不正确时返回None
+    pub fn get_coinbase_height(&self) -> Option<usize> {
+        if !self.is_coinbase() {
+            return None;
         }
-        Transaction {
-            id: self.id.clone(),
-            vin: inputs,
-            vout: outputs,
+        let data = match self.vin[0].get_script_sig().get_ops() {
+            [Op::PushData(data)] => data,
+            _ => return None,
+        };
+        if data.len() < 8 || data.len() > 8 + MAX_COINBASE_DATA {
+            return None;
         }
+        let height = u64::from_le_bytes(data[..8].try_into().ok()?);
+        usize::try_from(height).ok()
     }
 
     pub fn get_id(&self) -> String {
@@ -173,4 +385,69 @@ impl Transaction {
     pub fn get_vout(&self) -> &[TxOutput] {
         self.vout.as_slice()
     }
+
+    pub fn get_lock_time(&self) -> u32 {
+        self.lock_time
+    }
+
+    // 交易所有输出的金额总和，溢出时返回None
+    pub fn get_output_value(&self) -> Option<Amount> {
+        Amount::checked_sum(self.vout.iter().map(|out| out.get_value()))
+    }
+
+    // 交易序列化之后的字节数，用于计算手续费率
+    pub fn size(&self) -> usize {
+        serialize(self).map_or(0, |tx_ser| tx_ser.len())
+    }
+
+    // 交易中所有解锁脚本和锁定脚本包含的签名验证操作数量
+    pub fn sigop_count(&self) -> usize {
+        let sigops_in: usize = self.vin.iter().map(|vin| vin.get_script_sig().sigop_count()).sum();
+        let sigops_out: usize = self.vout.iter().map(|out| out.get_script_pubkey().sigop_count()).sum();
+        sigops_in + sigops_out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Block, ChainParams, Wallet};
+
+    impl Transaction {
+        // 使用wallet签名，将prev第vout个输出的全部金额转给to
+        pub(crate) fn new_signed_spend(prev: &Transaction, vout: usize, wallet: &Wallet, to: &str) -> Self {
+            let prev_out = &prev.vout[vout];
+            let mut tx = Transaction {
+                id: String::new(),
+                vin: vec![TxInput::new(prev.get_id(), vout)],
+                vout: vec![TxOutput::new(prev_out.get_value(), to)],
+                lock_time: 0,
+            };
+            tx.set_hash();
+
+            let sighash = tx.signature_hash(0, prev_out.get_script_pubkey());
+            let signature = ecdsa_p256_sha256_sign_digest(wallet.get_private_key(), sighash.as_bytes());
+            tx.vin[0].set_script_sig(Script::new_p2pkh_sig(signature, wallet.get_public_key()));
+            tx
+        }
+    }
+
+    #[test]
+    fn tampered_script_sig_changes_merkle_root() {
+        let wallet = Wallet::new();
+        let addr = wallet.get_address(ChainParams::dev().address_version);
+        let coinbase = Transaction::new_coinbase(&addr, Amount::from_coins(50), 2, &[]);
+        let tx = Transaction::new_signed_spend(&coinbase, 0, &wallet, &addr);
+        let mut tampered = tx.clone();
+        tampered.vin[0].set_script_sig(Script::new_p2pkh_sig(vec![0; 64], wallet.get_public_key()));
+
+        // 交易id不包含解锁脚本，替换签名后id不变
+        assert!(tx.verify_hash() && tampered.verify_hash());
+        assert_eq!(tx.get_id(), tampered.get_id());
+        assert_ne!(tx.get_full_hash(), tampered.get_full_hash());
+
+        let block = Block::new_template(&[coinbase.clone(), tx], "", 0);
+        let forged = Block::new_template(&[coinbase, tampered], "", 0);
+        assert_ne!(block.get_header().get_txs_hash(), forged.get_header().get_txs_hash());
+    }
 }
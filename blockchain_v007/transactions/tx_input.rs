@@ -1,6 +1,27 @@
 use serde::{Serialize, Deserialize};
 
-use crate::hash_pub_key;
+use crate::{Op, Script};
+
+// 输入的序列号为该值时不启用任何时间锁，交易的lock_time也只有在存在非最终序列号的输入时才生效
+pub const SEQUENCE_FINAL: u32 = 0xffff_ffff;
+// 序列号设置该位时不启用相对时间锁
+pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+// 序列号设置该位时相对时间锁按时间计算，否则按区块数量计算
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+// 序列号中相对时间锁数值所在的位
+pub const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000_ffff;
+// 按时间计算的相对时间锁以2^9 = 512秒为单位
+pub const SEQUENCE_LOCKTIME_GRANULARITY: u32 = 9;
+
+/*
+ * 相对时间锁：输入引用的交易输出被打包之后，至少再经过多少个区块或多少秒，
+ * 该输入所在的交易才能被打包
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativeLock {
+    Blocks(usize),  // 区块数量
+    Seconds(i64),   // 秒数，按中位时间计算
+}
 
 /*
  * 交易输入数据结构
@@ -12,24 +33,48 @@ use crate::hash_pub_key;
 pub struct TxInput {
     txid: String,   // 前一笔交易的id
     vout: usize,    // 前一笔交易的输出自增序号
-    signature: Vec<u8>, // 交易发起方使用私钥对交易的签名
-    pub_key: Vec<u8>,   // 交易发起方的公钥
+    script_sig: Script, // 解锁脚本，例如P2PKH中交易发起方的签名和公钥
+    sequence: u32,  // 序列号，用于启用交易的lock_time和该输入的相对时间锁
 }
 
 impl TxInput {
-    pub fn new(txid: String, vout: usize, pub_key: Vec<u8>) -> Self {
+    // 引用txid交易的第vout个输出，解锁脚本在签名时填充
+    pub fn new(txid: String, vout: usize) -> Self {
+        Self::with_sequence(txid, vout, SEQUENCE_FINAL)
+    }
+
+    // 指定序列号的输入
+    pub fn with_sequence(txid: String, vout: usize, sequence: u32) -> Self {
         Self {
             txid,
             vout,
-            signature: vec![],
-            pub_key,
+            script_sig: Script::default(),
+            sequence,
         }
     }
 
-    // 判断交易发起方同签名是否一致，一致才能解锁一个历史交易的输出
-    pub fn is_unlock_output(&self, pub_key_hash: &[u8]) -> bool {
-        let locked_hash = hash_pub_key(&self.pub_key);
-        locked_hash.eq(pub_key_hash)
+    /*
+     * coinbase交易的输入：不引用任何之前的交易输出，
+     * 解锁脚本中只压入区块高度和矿工自定义的数据
+     */
+    pub fn new_coinbase(data: Vec<u8>) -> Self {
+        Self {
+            txid: String::new(),
+            vout: 0,
+            script_sig: Script::new(vec![Op::PushData(data)]),
+            sequence: SEQUENCE_FINAL,
+        }
+    }
+
+    // 按区块数量计算的相对时间锁对应的序列号
+    pub fn sequence_from_blocks(blocks: u16) -> u32 {
+        blocks as u32
+    }
+
+    // 按时间计算的相对时间锁对应的序列号，秒数向上取整到512秒
+    pub fn sequence_from_seconds(seconds: u32) -> u32 {
+        let units = (seconds + (1 << SEQUENCE_LOCKTIME_GRANULARITY) - 1) >> SEQUENCE_LOCKTIME_GRANULARITY;
+        SEQUENCE_LOCKTIME_TYPE_FLAG | units.min(SEQUENCE_LOCKTIME_MASK)
     }
 
     pub fn get_txid(&self) -> String {
@@ -40,19 +85,28 @@ impl TxInput {
         self.vout
     }
 
-    pub fn get_pub_key(&self) -> &[u8] {
-        self.pub_key.as_slice()
+    pub fn get_script_sig(&self) -> &Script {
+        &self.script_sig
     }
 
-    pub fn get_signature(&self) -> &[u8] {
-        self.signature.as_slice()
+    pub fn set_script_sig(&mut self, script_sig: Script) {
+        self.script_sig = script_sig
     }
 
-    pub fn set_signature(&mut self, signature: Vec<u8>) {
-        self.signature = signature
+    pub fn get_sequence(&self) -> u32 {
+        self.sequence
     }
 
-    pub fn set_pub_key(&mut self, pub_key: &[u8]) {
-        self.pub_key = pub_key.to_vec();
+    // 序列号中的相对时间锁，未启用时返回None
+    pub fn get_relative_lock(&self) -> Option<RelativeLock> {
+        if self.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            return None;
+        }
+        let value = self.sequence & SEQUENCE_LOCKTIME_MASK;
+        if self.sequence & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+            Some(RelativeLock::Seconds((value as i64) << SEQUENCE_LOCKTIME_GRANULARITY))
+        } else {
+            Some(RelativeLock::Blocks(value as usize))
+        }
     }
 }
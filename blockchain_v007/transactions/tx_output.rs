@@ -1,6 +1,6 @@
 use serde::{Serialize, Deserialize};
 
-use crate::{utils::base58_decode, ADDRESS_CHECKSUM_LEN};
+use crate::{Amount, Script, address_to_pub_key_hash};
 
 /*
  * 交易输出数据结构，这是一个新的UTXO账户。
@@ -8,37 +8,35 @@ use crate::{utils::base58_decode, ADDRESS_CHECKSUM_LEN};
  */
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct TxOutput {
-    value: i32,         // 账户余额
-    pub_key_hash: Vec<u8>,    // 账户公钥hash
+    value: Amount,      // 账户余额
+    script_pubkey: Script,  // 锁定脚本，花费该输出时必须由解锁脚本满足
 }
 
 impl TxOutput {
-    pub fn new(value: i32, to_addr: &str) -> Self {
-        let mut output = TxOutput {
-            value,
-            pub_key_hash: vec![],
-        };
-        output.lock(to_addr);
-        output
+    // 支付给地址的输出，使用P2PKH锁定脚本
+    pub fn new(value: Amount, to_addr: &str) -> Self {
+        Self::with_script(value, Script::new_p2pkh(&address_to_pub_key_hash(to_addr)))
     }
 
-    // 从地址中截取出public key hash值
-    fn lock(&mut self, address: &str) {
-        let payload = base58_decode(address);
-        let pub_key_hash = payload[1..payload.len() - ADDRESS_CHECKSUM_LEN].to_vec();
-        self.pub_key_hash = pub_key_hash
+    // 使用任意锁定脚本的输出
+    pub fn with_script(value: Amount, script_pubkey: Script) -> Self {
+        Self {
+            value,
+            script_pubkey,
+        }
     }
 
-    // 判断address地址是否有拥有该输出
+    // 判断该输出是否支付给地址中的hash：P2PKH输出的公钥hash，或者P2SH输出的脚本hash
     pub fn is_locked(&self, pub_key_hash: &[u8]) -> bool {
-        self.pub_key_hash.eq(pub_key_hash)
+        self.script_pubkey.get_pub_key_hash() == Some(pub_key_hash)
+            || self.script_pubkey.get_script_hash() == Some(pub_key_hash)
     }
 
-    pub fn get_value(&self) -> i32 {
+    pub fn get_value(&self) -> Amount {
         self.value
     }
 
-    pub fn get_pub_key_hash(&self) -> &[u8] {
-        self.pub_key_hash.as_slice()
+    pub fn get_script_pubkey(&self) -> &Script {
+        &self.script_pubkey
     }
 }
@@ -1,45 +1,99 @@
 use std::{collections::HashMap, sync::Arc};
+use serde::{Serialize, Deserialize};
 
-use crate::{KVStorage, Blockchain, error::BlockchainError};
+use crate::{Amount, KVStorage, Blockchain, TxOutput, error::BlockchainError};
+
+// coinbase交易的输出至少需要经过多少个区块才能花费
+pub const COINBASE_MATURITY: usize = 10;
+
+/* 一个未花费的交易输出，以及它所在区块的信息 */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UtxoEntry {
+    vout: usize,            // 输出在交易中的序号
+    output: TxOutput,       // 交易输出
+    height: usize,          // 交易所在区块的高度
+    is_coinbase: bool,      // 是否是coinbase交易的输出
+}
+
+impl UtxoEntry {
+    pub fn new(vout: usize, output: TxOutput, height: usize, is_coinbase: bool) -> Self {
+        Self {
+            vout,
+            output,
+            height,
+            is_coinbase,
+        }
+    }
+
+    // 判断该输出能否被高度为spend_height的区块中的交易花费
+    pub fn is_mature(&self, spend_height: usize, maturity: usize) -> bool {
+        !self.is_coinbase || spend_height >= self.height + maturity
+    }
+
+    pub fn get_vout(&self) -> usize {
+        self.vout
+    }
+
+    pub fn get_output(&self) -> &TxOutput {
+        &self.output
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.is_coinbase
+    }
+}
 
 /* 可花费交易账户集合 */
 pub struct UTXOSet<T> {
-    storage: Arc<T>
+    storage: Arc<T>,
+    coinbase_maturity: usize,   // coinbase输出的成熟深度，必须与区块链一致
 }
 
 impl<T: KVStorage> UTXOSet<T> {
     pub fn new(storage: Arc<T>) -> Self {
+        Self::with_maturity(storage, COINBASE_MATURITY)
+    }
+
+    pub fn with_maturity(storage: Arc<T>, coinbase_maturity: usize) -> Self {
         Self {
-            storage
+            storage,
+            coinbase_maturity,
         }
     }
 
-    // 每次交易之后，需要清空utxo集合，并且重新生成utxo集合
+    // utxo集合随区块的连接和断开由区块链维护，只在需要时根据主链完整重建
     pub fn reindex(&self, bc: &Blockchain<T>) -> Result<(), BlockchainError> {
-        self.storage.clear_utxo_set();
-        let map = bc.find_utxo();
-        for (txid, outs) in map {
-            // 将新生成的utxo账户写入数据库
-            self.storage.write_utxo(&txid, outs)?;
-        }
-        Ok(())
+        bc.reindex_utxos()
     }
 
-    // 查找public_key_hash地址拥有的所有未花费账户
-    pub fn find_spendable_outputs(&self, public_key_hash: &[u8], amount: i32) -> (i32, HashMap<String, Vec<usize>>) {
+    // 下一个区块的高度，即新交易被打包时所在的高度
+    fn next_height(&self) -> usize {
+        self.storage.get_height().ok().flatten().unwrap_or_default() + 1
+    }
+
+    // 查找public_key_hash地址拥有的已经成熟的未花费账户
+    pub fn find_spendable_outputs(&self, public_key_hash: &[u8], amount: Amount) -> (Amount, HashMap<String, Vec<usize>>) {
         // 所有未花费账户存储在hashmap中
         let mut unspent_outpus = HashMap::new();
         // public_key_hash对应账户所有未花费账户的余额总和
-        let mut accumulated = 0;
+        let mut accumulated = Amount::ZERO;
         let utxo_set = self.storage.get_utxo_set();
+        let spend_height = self.next_height();
 
         for (txid, outs) in utxo_set.iter() {
-            for (idx, out) in outs.iter().enumerate() {
-                if out.is_locked(public_key_hash) && accumulated < amount {
-                    accumulated += out.get_value();
+            for entry in outs {
+                if !entry.is_mature(spend_height, self.coinbase_maturity) {
+                    continue;
+                }
+                if entry.get_output().is_locked(public_key_hash) && accumulated < amount {
+                    accumulated = accumulated.saturating_add(entry.get_output().get_value());
                     unspent_outpus.entry(txid.to_string())
-                        .and_modify(|v: &mut Vec<usize>| v.push(idx))
-                        .or_insert(vec![idx]);
+                        .and_modify(|v: &mut Vec<usize>| v.push(entry.get_vout()))
+                        .or_insert(vec![entry.get_vout()]);
                 }
             }
         }
@@ -47,16 +101,30 @@ impl<T: KVStorage> UTXOSet<T> {
         (accumulated, unspent_outpus)
     }
 
-    // 计算public_key_hash地址拥有的余额
-    pub fn get_balance(&self, public_key_hash: &[u8]) -> i32 {
+    // 计算public_key_hash地址拥有的可花费余额，不包括尚未成熟的coinbase输出
+    pub fn get_balance(&self, public_key_hash: &[u8]) -> Amount {
+        let spend_height = self.next_height();
+        self.sum_outputs(public_key_hash, |entry| entry.is_mature(spend_height, self.coinbase_maturity))
+    }
+
+    // 计算public_key_hash地址尚未成熟的coinbase输出的余额
+    pub fn get_immature_balance(&self, public_key_hash: &[u8]) -> Amount {
+        let spend_height = self.next_height();
+        self.sum_outputs(public_key_hash, |entry| !entry.is_mature(spend_height, self.coinbase_maturity))
+    }
+
+    fn sum_outputs<F>(&self, public_key_hash: &[u8], filter: F) -> Amount
+    where
+        F: Fn(&UtxoEntry) -> bool
+    {
         // public_key_hash对应账户所有未花费账户的余额总和
-        let mut balance = 0;
+        let mut balance = Amount::ZERO;
         let utxo_set = self.storage.get_utxo_set();
 
         for (_, outs) in utxo_set.iter() {
-            for (_, out) in outs.iter().enumerate() {
-                if out.is_locked(public_key_hash) {
-                    balance += out.get_value();
+            for entry in outs {
+                if entry.get_output().is_locked(public_key_hash) && filter(entry) {
+                    balance = balance.saturating_add(entry.get_output().get_value());
                 }
             }
         }
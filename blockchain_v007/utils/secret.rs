@@ -2,7 +2,7 @@ use std::iter::repeat;
 
 use crypto::digest::Digest;
 use crypto::ripemd160::Ripemd160;
-use ring::rand::SystemRandom;
+use ring::rand::{SecureRandom, SystemRandom};
 use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING, ECDSA_P256_SHA256_FIXED};
 use ring::digest::{Context, SHA256};
 
@@ -13,6 +13,13 @@ pub fn new_private_key() -> Vec<u8> {
     pkcs8.as_ref().to_vec()
 }
 
+/* 生成len字节的随机数据，例如哈希时间锁合约的原像 */
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let mut data = vec![0; len];
+    SystemRandom::new().fill(&mut data).unwrap();
+    data
+}
+
 pub fn sha256_digest(data: &[u8]) -> Vec<u8> {
     let mut context = Context::new(&SHA256);
     context.update(data);
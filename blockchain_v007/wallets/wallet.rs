@@ -1,8 +1,7 @@
 use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING, KeyPair};
 use serde::{Serialize, Deserialize};
-use crate::utils::{new_private_key, base58_encode, sha256_digest, ripemd160_digest};
+use crate::{Script, utils::{new_private_key, base58_encode, base58_decode, sha256_digest, ripemd160_digest}};
 
-const VERSION: u8 = 0x00;
 pub const ADDRESS_CHECKSUM_LEN: usize = 4;
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -28,19 +27,11 @@ impl Wallet {
         }
     }
 
-    // 根据公钥计算地址
-    pub fn get_address(&self) -> String {
+    // 根据公钥计算地址，version是网络参数中的地址版本前缀
+    pub fn get_address(&self, version: u8) -> String {
         // 对公钥计算hash
         let pub_key_hash = hash_pub_key(self.public_key.as_slice());
-        let mut payload = vec![];
-        // 给哈希值加上版本前缀，这里硬编码为 const VERSION: u8 = 0x00。
-        payload.push(VERSION);
-        payload.extend(pub_key_hash.as_slice());
-        // 计算校验和
-        let checksum = checksum(payload.as_slice());
-        payload.extend(checksum.as_slice());
-        // 使用 Base58 对 version+PubKeyHash+checksum 组合进行编码
-        base58_encode(payload.as_slice())
+        encode_address(version, &pub_key_hash)
     }
 
     pub fn get_private_key(&self) -> &[u8] {
@@ -52,6 +43,23 @@ impl Wallet {
     }
 }
 
+// 根据赎回脚本（例如多重签名脚本）计算P2SH地址，version是网络参数中的脚本地址版本前缀
+pub fn get_script_address(redeem_script: &Script, version: u8) -> String {
+    encode_address(version, &redeem_script.get_hash())
+}
+
+// 使用 Base58 对 version+hash+checksum 组合进行编码，hash为公钥hash或者脚本hash
+pub fn encode_address(version: u8, hash: &[u8]) -> String {
+    let mut payload = vec![];
+    // 给哈希值加上版本前缀，不同网络、不同类型的地址前缀不同
+    payload.push(version);
+    payload.extend(hash);
+    // 计算校验和
+    let checksum = checksum(payload.as_slice());
+    payload.extend(checksum.as_slice());
+    base58_encode(payload.as_slice())
+}
+
 /*
  * 1. 使用SHA256对公钥进行一次哈希
  * 2. 对结果使用RIPEMD160进行二次哈希
@@ -68,3 +76,22 @@ pub fn checksum(payload: &[u8]) -> Vec<u8> {
     let second_sha = sha256_digest(&first_sha);
     second_sha[0..ADDRESS_CHECKSUM_LEN].to_vec()
 }
+
+// 校验地址格式：Base58解码成功，且校验和正确
+pub fn validate_address(address: &str) -> bool {
+    let payload = match bs58::decode(address).into_vec() {
+        Ok(payload) => payload,
+        Err(_) => return false,
+    };
+    if payload.len() <= ADDRESS_CHECKSUM_LEN + 1 {
+        return false;
+    }
+    let (data, sum) = payload.split_at(payload.len() - ADDRESS_CHECKSUM_LEN);
+    checksum(data) == sum
+}
+
+// 从地址中截取出public key hash值（P2SH地址为脚本hash），去掉版本前缀和校验和
+pub fn address_to_pub_key_hash(address: &str) -> Vec<u8> {
+    let payload = base58_decode(address);
+    payload[1..payload.len() - ADDRESS_CHECKSUM_LEN].to_vec()
+}
@@ -3,15 +3,16 @@ use std::{collections::HashMap, env::current_dir, fs};
 
 use serde::{Serialize, Deserialize};
 
-use crate::{Wallet, utils::{serialize, deserialize}, error::BlockchainError};
+use crate::{Wallet, Script, hash_pub_key, MAX_PUBKEYS_PER_MULTISIG, get_script_address, utils::{serialize, deserialize}, error::BlockchainError};
 
 // 将wallet数据结构保存在本地wallet.dat文件中
 pub const WALLET_FILE: &str = "wallet.dat";
 
-// 从address到wallet的映射
+// 从address到wallet的映射，以及从脚本地址到赎回脚本的映射
 #[derive(Serialize, Deserialize)]
 pub struct Wallets {
     wallets: HashMap<String, Wallet>,
+    scripts: HashMap<String, Script>,   // 本地参与的多重签名地址及其赎回脚本
 }
 
 impl Wallets {
@@ -20,20 +21,51 @@ impl Wallets {
         wallets
     }
 
-    pub fn create_wallet(&mut self) -> String {
+    // 创建一个新钱包，返回其在version对应网络中的地址
+    pub fn create_wallet(&mut self, version: u8) -> String {
         let wallet = Wallet::new();
-        let address = wallet.get_address();
+        let address = wallet.get_address(version);
         self.wallets.insert(address.clone(), wallet);
         self.save_wallet_to_file().unwrap();
         address
     }
 
+    /*
+     * 创建required-of-n多重签名地址，返回其在version对应网络中的脚本地址
+     * pub_keys: 参与者的公钥，花费时签名必须按照该顺序排列
+     */
+    pub fn create_multisig(&mut self, required: usize, pub_keys: &[Vec<u8>], version: u8) -> Result<String, BlockchainError> {
+        if required == 0 || required > pub_keys.len() || pub_keys.len() > MAX_PUBKEYS_PER_MULTISIG {
+            return Err(BlockchainError::InvalidMultisig(required, pub_keys.len()));
+        }
+        let redeem_script = Script::new_multisig(required, pub_keys);
+        let address = get_script_address(&redeem_script, version);
+        self.scripts.insert(address.clone(), redeem_script);
+        self.save_wallet_to_file()?;
+        Ok(address)
+    }
+
     pub fn get_wallet(&self, address: &str) -> Option<&Wallet> {
         self.wallets.get(address)
     }
 
+    // 根据公钥hash查找本地钱包，用于对P2PKH输入签名
+    pub fn get_wallet_by_pub_key_hash(&self, pub_key_hash: &[u8]) -> Option<&Wallet> {
+        self.wallets.values().find(|wallet| hash_pub_key(wallet.get_public_key()) == pub_key_hash)
+    }
+
+    // 根据公钥查找本地钱包，用于对多重签名输入签名
+    pub fn get_wallet_by_pub_key(&self, pub_key: &[u8]) -> Option<&Wallet> {
+        self.wallets.values().find(|wallet| wallet.get_public_key() == pub_key)
+    }
+
+    // 根据脚本hash查找本地保存的赎回脚本
+    pub fn get_script_by_hash(&self, script_hash: &[u8]) -> Option<&Script> {
+        self.scripts.values().find(|script| script.get_hash() == script_hash)
+    }
+
     pub fn get_addresses(&self) -> Vec<&String> {
-        self.wallets.keys().collect()
+        self.wallets.keys().chain(self.scripts.keys()).collect()
     }
 
     // 将wallet数据存储在本地文件
@@ -52,13 +84,19 @@ impl Wallets {
         if !path.exists() {
             let wallets = Wallets {
                 wallets: HashMap::new(),
+                scripts: HashMap::new(),
             };
             return Ok(wallets);
         }
 
         let wallets_ser = fs::read(&path).unwrap();
-        let wallets = deserialize(&wallets_ser);
-        wallets
+        // 旧版本的wallet.dat只保存了wallet，没有多重签名脚本
+        deserialize(&wallets_ser).or_else(|_| {
+            Ok(Wallets {
+                wallets: deserialize(&wallets_ser)?,
+                scripts: HashMap::new(),
+            })
+        })
     }
 }
 